@@ -0,0 +1,53 @@
+//! [`criterion`] benchmarks of [`Cwd`] for stable toolchains, run with `cargo bench --bench cwd`.
+#![expect(
+    missing_docs,
+    reason = "generated by criterion and shared with the tests"
+)]
+
+use core::time::Duration;
+use criterion::{criterion_group, criterion_main, Criterion};
+use current_dir::Cwd;
+
+/// Shared with the tests for [`test_dir!`] and [`test_utilities::reset_cwd()`].
+#[expect(
+    dead_code,
+    unused_imports,
+    unused_macros,
+    clippy::missing_docs_in_private_items,
+    clippy::wildcard_imports,
+    clippy::unwrap_used,
+    clippy::expect_used,
+    clippy::single_call_fn,
+    reason = "only partially used by the benchmarks"
+)]
+mod test_utilities {
+    include!("../src/test_utilities.rs");
+}
+
+/// Benchmarks [`Cwd::get()`], [`Cwd::set()`] and both combined, locking the [`Cwd`] once for the group.
+#[expect(clippy::single_call_fn, reason = "registered with criterion")]
+fn bench_cwd(criterion: &mut Criterion) {
+    let test_dir = test_dir!();
+    let timeout = Duration::from_secs(10);
+    let benched =
+        test_utilities::yield_lock_poisoned(Cwd::mutex(), timeout).map(|mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            let mut group = criterion.benchmark_group("cwd");
+            group.bench_function("get", |bencher| bencher.iter(|| cwd.get()));
+            group.bench_function("set", |bencher| bencher.iter(|| cwd.set(&*test_dir)));
+            group.bench_function("set_and_get", |bencher| {
+                bencher.iter(|| cwd.get().map(|path| cwd.set(path)));
+            });
+            group.finish();
+        });
+    assert!(
+        benched.is_some(),
+        "benchmark acquired Cwd lock within {}s",
+        timeout.as_secs()
+    );
+}
+
+criterion_group!(benches, bench_cwd);
+criterion_main!(benches);
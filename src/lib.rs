@@ -1,25 +1,62 @@
 #![doc = include_str!("../README.md")]
 #![cfg_attr(feature = "unstable", feature(test))]
 
+extern crate alloc;
+
+use alloc::{collections::VecDeque, sync::Arc};
+#[cfg(feature = "tokio")]
+use core::future::Future;
 use core::{
     cell::Cell,
+    error::Error,
     fmt,
+    mem::{self, ManuallyDrop},
     ops::{Deref, DerefMut},
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
 };
 #[expect(clippy::useless_attribute, reason = "false positive")]
 use std::env;
 use std::{
-    io,
-    path::{Path, PathBuf},
-    sync::Mutex,
+    ffi::{OsStr, OsString},
+    fs, io,
+    panic::{self, PanicHookInfo},
+    path::{Component, Path, PathBuf},
+    process::{Child, Command},
+};
+
+// The `Cwd` lock, substituted when model checking with `RUSTFLAGS="--cfg loom" cargo test`.
+#[cfg(all(test, loom))]
+use loom::sync::{Mutex, MutexGuard};
+#[cfg(all(feature = "signal", unix))]
+use signal_hook::{
+    flag,
+    iterator::{Handle, Signals},
+    low_level,
+};
+#[cfg(all(feature = "signal", unix))]
+use std::sync::Mutex as StdMutex;
+#[cfg(not(all(test, loom)))]
+#[expect(clippy::useless_attribute, reason = "false positive")]
+#[expect(clippy::cfg_not_test, reason = "substituted when model checking")]
+use std::sync::{Mutex, MutexGuard};
+use std::sync::{PoisonError, TryLockError};
+use std::{thread, time::Instant};
+#[cfg(feature = "tokio")]
+use tokio::{
+    sync::{oneshot, Mutex as AsyncMutex, MutexGuard as AsyncMutexGuard},
+    task,
 };
 
 mod sealed;
 
 #[cfg(test)]
+#[cfg(not(loom))]
 mod test_utilities;
 
 #[cfg(test)]
+#[cfg(not(loom))]
 macro_rules! mutex_test {
     ($mutex:expr, $test:expr, $timeout:expr) => {
         assert!(
@@ -43,6 +80,156 @@ macro_rules! mutex_test {
         mutex_test!($($args)+, core::time::Duration::from_millis(100))
     };
 }
+/// Compares `left` and `right` in their canonical forms, falling back to their
+/// [lexically normalized](Cwd::lexically_normalize()) forms if either can't be canonicalized.
+fn is_same_path(left: &Path, right: &Path) -> bool {
+    match (fs::canonicalize(left), fs::canonicalize(right)) {
+        (Ok(canonical_left), Ok(canonical_right)) => canonical_left == canonical_right,
+        _ => Cwd::lexically_normalize(left) == Cwd::lexically_normalize(right),
+    }
+}
+
+/// Removes the [`CurDir`](Component::CurDir) components and trailing separators of `path`, which the filesystem
+/// ignores, returning `.` if nothing remains of a non-empty `path` so an empty `path` is still rejected.
+#[expect(clippy::single_call_fn, reason = "readability and logical separation")]
+fn strip_cur_dirs(path: &Path) -> PathBuf {
+    let stripped: PathBuf = path
+        .components()
+        .filter(|component| *component != Component::CurDir)
+        .collect();
+    if stripped.as_os_str().is_empty() && !path.as_os_str().is_empty() {
+        PathBuf::from(Component::CurDir.as_os_str())
+    } else {
+        stripped
+    }
+}
+
+/// Suspends the default action of each of the `signals` while they are handled by a [`SignalRestoreHandle`],
+/// registering it to be emulated once they no longer are, as the registered handlers are never removed.
+///
+/// # Errors
+/// The default action of a signal cannot be registered as per [`flag::register_conditional_default()`], in which
+/// case none of the `signals` are suspended.
+#[cfg(all(feature = "signal", unix))]
+#[expect(clippy::single_call_fn, reason = "readability and logical separation")]
+fn suspend_signal_defaults(signals: &[i32]) -> io::Result<()> {
+    let mut defaults = SIGNAL_DEFAULTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let mut suspended = 0_usize;
+    let result = signals.iter().try_for_each(|&signal| {
+        let registered = defaults
+            .iter_mut()
+            .find(|entry| entry.0 == signal)
+            .map(|entry| {
+                entry.1 = entry.1.saturating_add(1);
+                entry.2.store(false, Ordering::SeqCst);
+            });
+        if registered.is_some() {
+            Ok(())
+        } else {
+            let condition = Arc::new(AtomicBool::new(false));
+            flag::register_conditional_default(signal, Arc::clone(&condition))
+                .map(|_| defaults.push((signal, 1, condition)))
+        }
+        .inspect(|&()| suspended = suspended.saturating_add(1))
+    });
+    if result.is_err() {
+        resume_signal_defaults(&mut defaults, signals.iter().take(suspended));
+    }
+    result
+}
+
+/// Resumes the default action of each of the `signals` no longer handled by any [`SignalRestoreHandle`].
+#[cfg(all(feature = "signal", unix))]
+fn resume_signal_defaults<'signal, I: Iterator<Item = &'signal i32>>(
+    defaults: &mut [(i32, usize, Arc<AtomicBool>)],
+    signals: I,
+) {
+    for signal in signals {
+        if let Some(entry) = defaults.iter_mut().find(|entry| entry.0 == *signal) {
+            entry.1 = entry.1.saturating_sub(1);
+            if entry.1 == 0 {
+                entry.2.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+/// Resets the current working directory to `baseline` on receiving `signal`, through the [`Cwd::mutex()`] if it is
+/// available, otherwise directly through the [`SysProvider`] if the default action of `signal` terminates the
+/// process, as any [`CwdGuard`] holding the lock will not get to reset it.
+#[cfg(all(feature = "signal", unix))]
+#[cfg_attr(
+    not(test),
+    expect(clippy::single_call_fn, reason = "readability and logical separation")
+)]
+fn restore_on_signal(baseline: &Path, signal: i32) {
+    use signal_hook::consts::{
+        SIGCHLD, SIGCONT, SIGSTOP, SIGTSTP, SIGTTIN, SIGTTOU, SIGURG, SIGWINCH,
+    };
+
+    match Cwd::try_lock() {
+        Ok(mut locked_cwd) => drop(locked_cwd.set(baseline)),
+        Err(TryLockKind::WouldBlock) => {
+            if !matches!(
+                signal,
+                SIGCHLD | SIGCONT | SIGSTOP | SIGTSTP | SIGTTIN | SIGTTOU | SIGURG | SIGWINCH
+            ) {
+                drop(SysProvider.set(baseline));
+            }
+        }
+    }
+}
+
+/// The address of `hook`, identifying it while it is installed.
+#[expect(clippy::as_conversions, reason = "only compared for identity")]
+fn panic_hook_address(hook: &PanicHook) -> usize {
+    ptr::from_ref(&**hook).cast::<()>() as usize
+}
+
+/// Expresses `target` relative to `base`, both absolute, or returns `target` as is if they share no root or prefix.
+fn relative_path(base: &Path, target: &Path) -> PathBuf {
+    let common = base
+        .components()
+        .zip(target.components())
+        .take_while(|&(base_component, target_component)| base_component == target_component)
+        .count();
+    if common == 0 {
+        target.to_path_buf()
+    } else {
+        let relative: PathBuf = base
+            .components()
+            .skip(common)
+            .map(|_| Component::ParentDir)
+            .chain(target.components().skip(common))
+            .collect();
+        if relative.as_os_str().is_empty() {
+            PathBuf::from(Component::CurDir.as_os_str())
+        } else {
+            relative
+        }
+    }
+}
+
+/// The raw OS error for a stale network filesystem handle, i.e. `ESTALE`, if any.
+const ESTALE: Option<i32> = if cfg!(any(target_os = "linux", target_os = "android")) {
+    Some(116_i32)
+} else if cfg!(unix) {
+    Some(70_i32)
+} else {
+    None
+};
+
+/// Whether `err` may not recur when retried, e.g. during a network filesystem failover.
+#[expect(clippy::single_call_fn, reason = "readability and logical separation")]
+fn is_transient(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+    ) || (ESTALE.is_some() && err.raw_os_error() == ESTALE)
+}
+
 /// Allows cloning the contense of a [`Cell`] that implement [`Default`] and [`Clone`]
 fn clone_cell_value<T: Default + Clone>(cell: &Cell<T>) -> T {
     let value = cell.take();
@@ -67,20 +254,329 @@ mod cell_test {
 }
 
 /// The per-process shared memory for avoiding current working directory race conditions.
+#[cfg(not(all(test, loom)))]
+#[expect(clippy::cfg_not_test, reason = "substituted when model checking")]
 static CWD_MUTEX: Mutex<Cwd> = Mutex::new(Cwd::new());
+#[cfg(all(test, loom))]
+loom::lazy_static! {
+    /// The per-execution shared memory for avoiding current working directory race conditions.
+    static ref CWD_MUTEX: Mutex<Cwd> = Mutex::new(Cwd::new());
+}
+
+/// The per-process shared memory of async tasks, see [`AsyncCwd::lock()`].
+#[cfg(feature = "tokio")]
+static ASYNC_CWD_MUTEX: AsyncMutex<Cwd> = AsyncMutex::const_new(Cwd::new());
+
+/// The signals handled by [`Cwd::install_signal_restore()`], each with the number of [`SignalRestoreHandle`]s
+/// handling it and the condition that emulates its default action while there are none.
+#[cfg(all(feature = "signal", unix))]
+static SIGNAL_DEFAULTS: StdMutex<Vec<(i32, usize, Arc<AtomicBool>)>> = StdMutex::new(Vec::new());
+
+/// Whether the hook of [`Cwd::install_panic_restore_hook()`] is installed.
+static PANIC_RESTORE_HOOK_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// A hook called when a thread panics, as per [`panic::set_hook()`].
+type PanicHook = Box<dyn Fn(&PanicHookInfo<'_>) + Send + Sync>;
+
+thread_local! {
+    /// Whether the current thread holds the [`Cwd::mutex()`] through a [`LockedCwd`].
+    static HOLDS_CWD_LOCK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The source of truth for the current working directory that a [`Cwd`] reads and changes.
+pub trait CwdProvider {
+    /// Returns the current working directory.
+    ///
+    /// # Errors
+    /// The current working directory cannot be retrieved.
+    fn current(&self) -> io::Result<PathBuf>;
+
+    /// Changes the current working directory to `path`.
+    ///
+    /// # Errors
+    /// The current working directory cannot be changed to `path`.
+    fn set(&mut self, path: &Path) -> io::Result<()>;
+
+    /// Returns the canonical form of `path`, resolving a relative `path` against [`current()`](Self::current()).
+    ///
+    /// By default `path` is resolved on the filesystem as per [`fs::canonicalize()`].
+    ///
+    /// # Errors
+    /// The current working directory cannot be retrieved or `path` cannot be canonicalized.
+    #[inline]
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        match self.current() {
+            Ok(cwd) => fs::canonicalize(cwd.join(path)),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// The [`CwdProvider`] for the current working directory of the process.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[expect(clippy::exhaustive_structs, reason = "unit struct")]
+pub struct SysProvider;
+impl CwdProvider for SysProvider {
+    /// Wrapper function for [`env::current_dir()`].
+    #[inline]
+    fn current(&self) -> io::Result<PathBuf> {
+        env::current_dir()
+    }
+
+    /// Wrapper function for [`env::set_current_dir()`].
+    #[inline]
+    fn set(&mut self, path: &Path) -> io::Result<()> {
+        env::set_current_dir(path)
+    }
+
+    /// Wrapper function for [`fs::canonicalize()`], which resolves a relative `path` against the current working
+    /// directory of the process.
+    #[inline]
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::canonicalize(path)
+    }
+}
+
+/// An in-memory [`CwdProvider`] for testing without changing the current working directory of the process.
+///
+/// Any directory can be [`set()`](CwdProvider::set()), relative paths are joined onto the current working directory,
+/// unless it was made to [fail](MockProvider::fail_set()). Paths are
+/// [canonicalized](CwdProvider::canonicalize()) [lexically](Cwd::lexically_normalize()), without accessing the
+/// filesystem.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MockProvider {
+    /// The mocked current working directory.
+    cwd: PathBuf,
+    /// The directories that fail to be set, with the kind of error returned.
+    failing: Vec<(PathBuf, io::ErrorKind)>,
+}
+impl MockProvider {
+    /// Creates a [`MockProvider`] starting in `cwd`.
+    #[inline]
+    #[must_use]
+    pub fn new<P: Into<PathBuf>>(cwd: P) -> Self {
+        Self {
+            cwd: cwd.into(),
+            failing: Vec::new(),
+        }
+    }
+
+    /// Makes [`set()`](CwdProvider::set()) fail with an error of `kind` when it would change to `path`, leaving the
+    /// mocked current working directory unchanged.
+    #[inline]
+    #[must_use]
+    pub fn fail_set<P: Into<PathBuf>>(mut self, path: P, kind: io::ErrorKind) -> Self {
+        self.failing.push((path.into(), kind));
+        self
+    }
+}
+impl CwdProvider for MockProvider {
+    #[inline]
+    fn current(&self) -> io::Result<PathBuf> {
+        Ok(self.cwd.clone())
+    }
+
+    #[inline]
+    fn set(&mut self, path: &Path) -> io::Result<()> {
+        let target = self.cwd.join(path);
+        self.failing
+            .iter()
+            .find(|failing| failing.0 == target)
+            .map_or_else(
+                || {
+                    self.cwd = target;
+                    Ok(())
+                },
+                |&(_, kind)| Err(io::Error::new(kind, "mocked failure")),
+            )
+    }
+
+    #[inline]
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Ok(Cwd::lexically_normalize(self.cwd.join(path)))
+    }
+}
+
+/// Where the [expected current working directory](Cwd::get_expected()) came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ExpectationSource {
+    /// Set explicitly, i.e. by [`Cwd::set()`] while [tracking](Cwd::track_expected()) or [`Cwd::set_expected()`].
+    Manual,
+    /// Observed by [`Cwd::get()`] while [tracking](Cwd::track_expected()).
+    Observed,
+    /// Set by a [`CwdGuard`] that panicked as it could not [`reset()`](CwdGuard::reset()), or a [`DeferredGuard`]
+    /// that could not [`set()`](Cwd::set()) its target.
+    PanicRecovery,
+}
+
+/// Where the current working directory is relative to another directory, see [`Cwd::diff_against()`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PathDiff {
+    /// The current working directory is the other directory.
+    Same,
+    /// The current working directory is within the other directory, by the relative path.
+    Below(PathBuf),
+    /// The other directory is within the current working directory, by the relative path.
+    Above(PathBuf),
+    /// Neither is within the other, the current working directory is the path relative to the other directory, or
+    /// absolute if they share no root.
+    Unrelated(PathBuf),
+}
+
+/// Why [`Cwd::try_lock()`] did not acquire the [`Cwd::mutex()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TryLockKind {
+    /// The lock is held elsewhere and acquiring it would block.
+    WouldBlock,
+}
+
+/// Why [`Cwd::with_dir_and_value()`] failed.
+///
+/// A failure to reset the current working directory takes priority over the error of the scope, as it leaves the
+/// process in an unexpected directory.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CwdError<E> {
+    /// The current working directory could not be retrieved or set to the directory, the scope was not run.
+    Setup(io::Error),
+    /// The scope failed and the current working directory was reset.
+    Scope(E),
+    /// The current working directory could not be reset, with the error of the scope if it also failed.
+    Reset(io::Error, Option<E>),
+}
+impl<E> fmt::Display for CwdError<E> {
+    #[inline]
+    #[expect(clippy::pattern_type_mismatch, reason = "binding the borrowed errors")]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Setup(err) => write!(f, "cannot enter the directory: {err}"),
+            Self::Scope(_) => f.write_str("the scope failed"),
+            Self::Reset(err, _) => {
+                write!(f, "cannot reset the current working directory: {err}")
+            }
+        }
+    }
+}
+#[expect(
+    clippy::missing_trait_methods,
+    reason = "the deprecated and unstable methods keep their defaults"
+)]
+impl<E: Error + 'static> Error for CwdError<E> {
+    #[inline]
+    #[expect(clippy::pattern_type_mismatch, reason = "binding the borrowed errors")]
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Setup(err) | Self::Reset(err, _) => Some(err),
+            Self::Scope(err) => Some(err),
+        }
+    }
+}
+
+/// Adds context to the [`io::Result`]s of this crate, e.g. `cwd.set(dir).cwd_context("while loading plugins")`.
+pub trait CwdResultExt<T>: sealed::Sealed {
+    /// Prefixes the message of an error with `context`, keeping its [`kind()`](io::Error::kind()) and the original
+    /// error as its [`source()`](Error::source()).
+    ///
+    /// # Errors
+    /// `self` is an error, with `context` added.
+    fn cwd_context<D: fmt::Display>(self, context: D) -> io::Result<T>;
+}
+impl<T> CwdResultExt<T> for io::Result<T> {
+    #[inline]
+    fn cwd_context<D: fmt::Display>(self, context: D) -> Self {
+        self.map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                ContextError {
+                    context: context.to_string(),
+                    source: err,
+                },
+            )
+        })
+    }
+}
+
+/// An [`io::Error`] with context added by [`CwdResultExt::cwd_context()`].
+#[derive(Debug)]
+struct ContextError {
+    /// What was happening when the error occurred.
+    context: String,
+    /// The original error.
+    source: io::Error,
+}
+impl fmt::Display for ContextError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.context, self.source)
+    }
+}
+#[expect(
+    clippy::missing_trait_methods,
+    reason = "the deprecated and unstable methods keep their defaults"
+)]
+impl Error for ContextError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// A handler called with the `(expected, actual)` current working directories when [`Cwd::get()`] notices they
+/// differ, see [`Cwd::on_drift()`].
+pub type DriftHandler = Box<dyn FnMut(&Path, &Path) + Send>;
 
 /// Wrapper type to help the usage of the current working directory for the process.
-pub struct Cwd {
-    /// The expected current working directory.
-    expected_cwd: Cell<Option<PathBuf>>,
+///
+/// The current working directory is accessed through a [`CwdProvider`], which is the process' by default.
+pub struct Cwd<C = SysProvider> {
+    /// The expected current working directory and where it came from.
+    #[expect(
+        clippy::struct_field_names,
+        reason = "the expected current working directory"
+    )]
+    expected_cwd: Cell<Option<(PathBuf, ExpectationSource)>>,
+    /// Whether to track the expected current working directory on each access.
+    track_expected: bool,
+    /// The number of live [`CwdGuard`]s borrowing this [`Cwd`].
+    depth: usize,
+    /// The current working directory before the last [`Cwd::set()`].
+    #[expect(
+        clippy::struct_field_names,
+        reason = "the previous current working directory"
+    )]
+    previous_cwd: Option<PathBuf>,
+    /// The canonical directory [`Cwd::set()`] is restricted to, if any.
+    jail: Option<PathBuf>,
+    /// The number of live [`FreezeGuard`]s making [`Cwd::set()`] fail.
+    frozen: usize,
+    /// Whether [`Cwd::set()`] only records the paths it would set.
+    dry_run: bool,
+    /// The paths [`Cwd::set()`] would have set while in dry run mode.
+    dry_run_log: Vec<PathBuf>,
+    /// When the current working directory was changed by [`Cwd::set()`] and to what, oldest first.
+    history: VecDeque<(Instant, PathBuf)>,
+    /// Called when [`Cwd::get()`] notices the current working directory is not the expected one.
+    drift_handler: Cell<Option<DriftHandler>>,
+    /// Whether to assert the [`Cwd::mutex()`] is held through [`Cwd::lock()`] on each access in debug builds.
+    verify_lock_held: bool,
+    /// The maximum length of the `history`, which is disabled when `0`.
+    history_capacity: usize,
+    /// The number of most recent `history` entries stepped back over by [`Cwd::undo()`].
+    history_undone: usize,
+    /// The source of truth for the current working directory.
+    provider: C,
 }
 impl Cwd {
     /// Creates the shared memory used by [`CwdGuard`]
-    #[expect(clippy::single_call_fn, reason = "better readability")]
+    #[cfg_attr(
+        not(feature = "tokio"),
+        expect(clippy::single_call_fn, reason = "better readability")
+    )]
     const fn new() -> Self {
-        Self {
-            expected_cwd: Cell::new(None),
-        }
+        Self::with_provider(SysProvider)
     }
 
     /// The [`Mutex`] ensuring the state of the current working directory.
@@ -92,56 +588,3779 @@ impl Cwd {
         &CWD_MUTEX
     }
 
+    /// Acquires the [`Cwd::mutex()`], blocking until it is available and recovering the lock if it is poisoned.
+    ///
+    /// A poisoned lock is acquired as is and remains poisoned, the current working directory may not be as expected,
+    /// see [`Cwd::get_expected()`].
+    ///
+    /// # Panics
+    /// If the current thread already holds the lock through [`Cwd::lock()`] or [`Cwd::lock_owned()`], rather than
+    /// deadlocking. Locks acquired
+    /// directly through [`Cwd::mutex()`] are not detected.
+    #[inline]
+    #[must_use]
+    pub fn lock() -> LockedCwd {
+        assert!(
+            !HOLDS_CWD_LOCK.get(),
+            "recursive cwd lock on the same thread"
+        );
+        let locked_cwd = Self::mutex().lock().unwrap_or_else(PoisonError::into_inner);
+        HOLDS_CWD_LOCK.set(true);
+        LockedCwd { locked_cwd }
+    }
+
+    /// Acquires the [`Cwd::mutex()`] as per [`Cwd::lock()`], for code that stores or returns the guard.
+    ///
+    /// The [`LockedCwd`] borrows nothing but the `'static` [`Cwd::mutex()`], so it can be stored or returned freely.
+    /// It must not be held across an `.await` that may block on another task acquiring the lock, which would
+    /// deadlock.
+    ///
+    /// # Panics
+    /// If the current thread already holds the lock, as per [`Cwd::lock()`].
+    #[inline]
+    #[must_use]
+    pub fn lock_owned() -> LockedCwd {
+        Self::lock()
+    }
+
+    /// Acquires the [`Cwd::mutex()`] as per [`Cwd::try_lock()`], sleeping for exponentially longer, up to 10ms,
+    /// between attempts rather than spinning, returning [`None`] if it is still held elsewhere after `max_wait`.
+    ///
+    /// A poisoned lock is acquired as is and remains poisoned, the current working directory may not be as expected,
+    /// see [`Cwd::get_expected()`].
+    #[inline]
+    #[must_use]
+    pub fn lock_with_backoff(max_wait: Duration) -> Option<LockedCwd> {
+        const MAX_BACKOFF: Duration = Duration::from_millis(10);
+        let deadline = Instant::now().checked_add(max_wait);
+        let mut backoff = Duration::from_micros(10);
+        loop {
+            match Self::try_lock() {
+                Ok(locked_cwd) => break Some(locked_cwd),
+                Err(TryLockKind::WouldBlock) => {
+                    let remaining = deadline.map_or(backoff, |deadline_instant| {
+                        deadline_instant.saturating_duration_since(Instant::now())
+                    });
+                    if remaining.is_zero() {
+                        break None;
+                    }
+                    thread::sleep(backoff.min(remaining));
+                    backoff = backoff.saturating_mul(2).min(MAX_BACKOFF);
+                }
+            }
+        }
+    }
+
+    /// Installs a panic hook, as per [`panic::set_hook()`], that resets the current working directory to its
+    /// [expected](Self::get_expected()) value at the time of the panic before calling the existing hook, returning
+    /// [`None`] if already installed.
+    ///
+    /// The reset is only attempted if the [`Cwd::mutex()`] is not held, e.g. by the panicking thread, whose
+    /// [`CwdGuard`]s [`reset()`](CwdGuard::reset()) as it unwinds, and there is an expectation. When the returned
+    /// [`PanicRestoreHook`] is dropped, the previous hook is restored if the hook is still installed, otherwise any
+    /// hook set since that chains to it is kept and it only calls the previous hook.
+    #[inline]
+    #[must_use]
+    pub fn install_panic_restore_hook() -> Option<PanicRestoreHook> {
+        PANIC_RESTORE_HOOK_INSTALLED
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .ok()
+            .map(|_| {
+                let previous_hook = Arc::new(panic::take_hook());
+                let chained_hook = Arc::clone(&previous_hook);
+                let resetting = Arc::new(AtomicBool::new(true));
+                let hook_resetting = Arc::clone(&resetting);
+                let hook: PanicHook = Box::new(move |info| {
+                    if hook_resetting.load(Ordering::Acquire) {
+                        if let Ok(mut locked_cwd) = Self::try_lock() {
+                            if let Some(expected_cwd) = locked_cwd.get_expected() {
+                                drop(locked_cwd.set(expected_cwd));
+                            }
+                        }
+                    }
+                    chained_hook(info);
+                });
+                let installed_hook = panic_hook_address(&hook);
+                panic::set_hook(hook);
+                PanicRestoreHook {
+                    previous_hook,
+                    resetting,
+                    installed_hook,
+                }
+            })
+    }
+
+    /// Registers handlers for `signals`, as per [`Signals::new()`], that reset the current working directory to its
+    /// [expected or current](Self::expected_or_current()) value at the time of the call before emulating the default
+    /// action, as per [`low_level::emulate_default_handler()`], e.g. terminating for `SIGINT` and `SIGTERM`.
+    ///
+    /// The signals are handled on a background thread that resets the current working directory through the
+    /// [`Cwd::mutex()`] if it is not held. While it is held, e.g. a [`CwdGuard`] is alive, the current working
+    /// directory is reset without the lock only if the default action terminates the process, otherwise it is left
+    /// unchanged, e.g. for `SIGWINCH` or `SIGTSTP`.
+    ///
+    /// The `signals` are assumed to have their default disposition. As registered signal handlers cannot be
+    /// removed, dropping the returned [`SignalRestoreHandle`] stops the reset but keeps emulating the default action,
+    /// rather than restoring a previous disposition such as being ignored. Handlers installed before the call are
+    /// still called first.
+    ///
+    /// `self` is borrowed mutably as the handlers change the current working directory, so they cannot be installed
+    /// through a [`CwdRef`].
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`expected_or_current()`](Self::expected_or_current()), any
+    /// of the `signals` cannot be handled as per [`Signals::new()`] or [`flag::register_conditional_default()`], or
+    /// the background thread cannot be spawned as per [`thread::Builder::spawn()`].
+    ///
+    /// # Panics
+    /// If any of the `signals` may not be handled, e.g. `SIGKILL`, as per [`Signals::new()`].
+    #[inline]
+    #[cfg(all(feature = "signal", unix))]
+    pub fn install_signal_restore(&mut self, signals: &[i32]) -> io::Result<SignalRestoreHandle> {
+        match (self.expected_or_current(), Signals::new(signals)) {
+            (Ok(baseline), Ok(mut handled_signals)) => match suspend_signal_defaults(signals) {
+                Ok(()) => {
+                    let mut restore_handle = SignalRestoreHandle {
+                        handle: handled_signals.handle(),
+                        signals: signals.to_vec(),
+                        thread: None,
+                    };
+                    thread::Builder::new()
+                        .name("current_dir signal restore".into())
+                        .spawn(move || {
+                            for signal in handled_signals.forever() {
+                                restore_on_signal(&baseline, signal);
+                                drop(low_level::emulate_default_handler(signal));
+                            }
+                        })
+                        .map(|thread| {
+                            restore_handle.thread = Some(thread);
+                            restore_handle
+                        })
+                }
+                Err(err) => Err(err),
+            },
+            (Err(err), _) | (_, Err(err)) => Err(err),
+        }
+    }
+
+    /// Acquires the [`Cwd::mutex()`] without blocking, recovering the lock if it is poisoned.
+    ///
+    /// A poisoned lock is acquired as is and remains poisoned, the current working directory may not be as expected,
+    /// see [`Cwd::get_expected()`].
+    ///
+    /// # Errors
+    /// The lock is held elsewhere, i.e. [`TryLockKind::WouldBlock`].
+    #[inline]
+    pub fn try_lock() -> Result<LockedCwd, TryLockKind> {
+        match Self::mutex().try_lock() {
+            Ok(locked_cwd) => Ok(locked_cwd),
+            Err(TryLockError::Poisoned(poisoned_locked_cwd)) => {
+                Ok(poisoned_locked_cwd.into_inner())
+            }
+            Err(TryLockError::WouldBlock) => Err(TryLockKind::WouldBlock),
+        }
+        .map(|locked_cwd| {
+            HOLDS_CWD_LOCK.set(true);
+            LockedCwd { locked_cwd }
+        })
+    }
+
+    /// Resolves the [`CurDir`](Component::CurDir) and [`ParentDir`](Component::ParentDir) components of `path`
+    /// without accessing the filesystem, unlike [`fs::canonicalize()`].
+    ///
+    /// Leading [`ParentDir`](Component::ParentDir)s of relative paths are kept, those above the root of absolute
+    /// paths are dropped and an empty result is `.`. As symlinks are not resolved, `link/..` may not be the
+    /// directory the filesystem would resolve it to.
+    #[inline]
+    #[must_use]
+    pub fn lexically_normalize<P: AsRef<Path>>(path: P) -> PathBuf {
+        let mut normalized = PathBuf::new();
+        for component in path.as_ref().components() {
+            match (component, normalized.components().next_back()) {
+                (Component::CurDir, _) | (Component::ParentDir, Some(Component::RootDir)) => {}
+                (Component::ParentDir, Some(Component::Normal(_))) => {
+                    normalized.pop();
+                }
+                _ => normalized.push(component),
+            }
+        }
+        if normalized.as_os_str().is_empty() {
+            PathBuf::from(Component::CurDir.as_os_str())
+        } else {
+            normalized
+        }
+    }
+
+    /// Returns whether `left` and `right` refer to the same directory, or other file, on the filesystem.
+    ///
+    /// Both paths are compared in their [canonical](fs::canonicalize()) forms, resolving symlinks, `.`, `..` and
+    /// trailing separators. On Unix, differing canonical paths are then compared by device and inode, as per
+    /// [`MetadataExt`](std::os::unix::fs::MetadataExt), to detect bind mounts and hard links. Elsewhere only the
+    /// canonical paths are compared, which may differ in case on case-insensitive filesystems.
+    ///
+    /// # Errors
+    /// Either path cannot be canonicalized as per [`fs::canonicalize()`], e.g. it does not exist, or on Unix its
+    /// metadata cannot be retrieved as per [`fs::metadata()`].
+    #[inline]
+    pub fn is_same_dir<L: AsRef<Path>, R: AsRef<Path>>(left: L, right: R) -> io::Result<bool> {
+        match (fs::canonicalize(left), fs::canonicalize(right)) {
+            (Ok(canonical_left), Ok(canonical_right)) if canonical_left == canonical_right => {
+                Ok(true)
+            }
+            #[cfg(unix)]
+            (Ok(canonical_left), Ok(canonical_right)) => {
+                use std::os::unix::fs::MetadataExt as _;
+
+                match (fs::metadata(canonical_left), fs::metadata(canonical_right)) {
+                    (Ok(left_metadata), Ok(right_metadata)) => Ok(left_metadata.dev()
+                        == right_metadata.dev()
+                        && left_metadata.ino() == right_metadata.ino()),
+                    (Err(err), _) | (_, Err(err)) => Err(err),
+                }
+            }
+            #[cfg(not(unix))]
+            (Ok(_), Ok(_)) => Ok(false),
+            (Err(err), _) | (_, Err(err)) => Err(err),
+        }
+    }
+}
+impl<C: CwdProvider> Cwd<C> {
+    /// Creates a [`Cwd`] accessing the current working directory through `provider`.
+    ///
+    /// This is generally useful with a [`MockProvider`] for testing, as only the [`Cwd::mutex()`] may access the
+    /// current working directory of the process.
+    #[inline]
+    #[must_use]
+    pub const fn with_provider(provider: C) -> Self {
+        Self {
+            expected_cwd: Cell::new(None),
+            track_expected: cfg!(feature = "full_expected_cwd"),
+            depth: 0,
+            previous_cwd: None,
+            jail: None,
+            frozen: 0,
+            dry_run: false,
+            dry_run_log: Vec::new(),
+            history: VecDeque::new(),
+            drift_handler: Cell::new(None),
+            verify_lock_held: false,
+            history_capacity: 0,
+            history_undone: 0,
+            provider,
+        }
+    }
+
+    /// Enables or disables tracking the expected current working directory on each access, overriding the
+    /// `full_expected_cwd` feature which enables tracking by default.
+    ///
+    /// When enabled, [`get()`](Self::get()) sets the expectation if there is none and [`set()`](Self::set()) always
+    /// sets the expectation, to the [canonical](fs::canonicalize()) form of its path if it can be resolved so that it
+    /// matches [`get()`](Self::get()). Any existing expectation is kept.
+    #[inline]
+    pub fn track_expected(&mut self, enabled: bool) {
+        self.track_expected = enabled;
+    }
+
+    /// Enables or disables asserting, in debug builds, that the current thread holds the [`Cwd::mutex()`] through a
+    /// [`LockedCwd`], e.g. from [`Cwd::lock()`] or [`Cwd::try_lock()`], whenever [`get()`](Self::get()) or
+    /// [`set()`](Self::set()) is called.
+    ///
+    /// This catches accessing a [`Cwd`] without the lock, but also locks acquired directly through
+    /// [`Cwd::mutex()`], which are not detected.
+    #[inline]
+    pub fn verify_lock_held(&mut self, enabled: bool) {
+        self.verify_lock_held = enabled;
+    }
+
+    /// Asserts the current thread holds the [`Cwd::mutex()`] if [verifying](Self::verify_lock_held()).
+    fn assert_lock_held(&self) {
+        debug_assert!(
+            !self.verify_lock_held || HOLDS_CWD_LOCK.get(),
+            "cwd accessed without Cwd::lock() held on this thread"
+        );
+    }
+
     /// Returns the expected current working directory if any.
-    /// By default the only expectations set are when this crate produces a panic.
+    /// By default the only expectations set are when this crate produces a panic, unless
+    /// [tracking](Self::track_expected()).
     #[must_use]
     #[inline]
     pub fn get_expected(&self) -> Option<PathBuf> {
+        self.expected_with_source().map(|(path, _)| path)
+    }
+
+    /// Returns the stored [expected current working directory](Self::get_expected()) if any.
+    ///
+    /// Unlike [`get_expected()`](Self::get_expected()), no expectation is observed from the current working directory
+    /// while [tracking](Self::track_expected()), telling a stored expectation apart from one that would be observed.
+    #[must_use]
+    #[inline]
+    pub fn try_clone_expected(&self) -> Option<PathBuf> {
+        clone_cell_value(&self.expected_cwd).map(|(path, _)| path)
+    }
+
+    /// Returns the [expected current working directory](Self::get_expected()) if any, otherwise the current working
+    /// directory as per [`get()`](Self::get()).
+    ///
+    /// Unlike [`get()`](Self::get()) while [tracking](Self::track_expected()), no expectation is set as a side
+    /// effect.
+    ///
+    /// # Errors
+    /// There is no expectation and the current directory cannot be retrieved as per [`get()`](Self::get()).
+    #[inline]
+    pub fn expected_or_current(&self) -> io::Result<PathBuf> {
+        clone_cell_value(&self.expected_cwd).map_or_else(
+            || {
+                self.assert_lock_held();
+                self.provider.current()
+            },
+            |(expected, _)| Ok(expected),
+        )
+    }
+
+    /// Returns the [expected current working directory](Self::get_expected()) if any and where it came from.
+    ///
+    /// This is generally useful for debugging why recovery is targeting a surprising directory.
+    #[must_use]
+    #[inline]
+    pub fn expected_with_source(&self) -> Option<(PathBuf, ExpectationSource)> {
         clone_cell_value(&self.expected_cwd).or_else(|| {
-            if cfg!(feature = "full_expected_cwd") {
-                self.get().ok()
+            if self.track_expected {
+                self.get()
+                    .ok()
+                    .map(|path| (path, ExpectationSource::Observed))
             } else {
                 None
             }
         })
     }
 
-    /// Wrapper function to ensure [`env::current_dir()`] is called with the [`Cwd`] borrowed.
+    /// Sets the expected current working directory without changing the current working directory, regardless of
+    /// [tracking](Self::track_expected()).
+    ///
+    /// This is generally useful when recovering from a poisoned [`Cwd::mutex()`] before the expected current working
+    /// directory can be set.
     #[inline]
-    #[doc(alias = "current_dir")]
-    #[expect(clippy::missing_errors_doc, reason = "Wrapper function")]
-    pub fn get(&self) -> io::Result<PathBuf> {
-        env::current_dir().inspect(|path| {
-            if cfg!(feature = "full_expected_cwd") && clone_cell_value(&self.expected_cwd).is_none()
-            {
-                self.expected_cwd.set(Some(path.clone()));
-            }
-        })
+    pub fn set_expected<P: Into<PathBuf>>(&mut self, path: P) {
+        self.expected_cwd
+            .set(Some((path.into(), ExpectationSource::Manual)));
     }
 
-    /// Wrapper function to ensure [`env::set_current_dir()`] is called with the [`Cwd`] borrowed.
+    /// Clears the expected current working directory without changing the current working directory, regardless of
+    /// [tracking](Self::track_expected()).
     #[inline]
-    #[doc(alias = "set_current_dir")]
-    #[expect(clippy::missing_errors_doc, reason = "Wrapper function")]
-    pub fn set<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
-        env::set_current_dir(&path).map(|()| {
-            if cfg!(feature = "full_expected_cwd") {
-                self.expected_cwd.set(Some(path.as_ref().to_path_buf()));
-            }
-        })
+    pub fn clear_expected(&mut self) {
+        self.expected_cwd.set(None);
     }
-}
-impl fmt::Debug for Cwd {
+
+    /// Returns whether the [expected current working directory](Self::get_expected()) is `path`, without accessing
+    /// the current working directory or recording an expectation while [tracking](Self::track_expected()).
+    ///
+    /// The paths are compared as given, see [`is_expected()`](Self::is_expected()) to compare the current working
+    /// directory in canonical form.
+    #[inline]
+    #[must_use]
+    pub fn expected_eq<P: AsRef<Path>>(&self, path: P) -> bool {
+        clone_cell_value(&self.expected_cwd).is_some_and(|(expected, _)| expected == path.as_ref())
+    }
+
+    /// Returns whether the current working directory is the [expected](Self::get_expected()) one, this is always
+    /// the case when there are no expectations.
+    ///
+    /// The directories are compared in their canonical forms where possible.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`get()`](Self::get()).
+    #[inline]
+    pub fn is_expected(&self) -> io::Result<bool> {
+        self.drift().map(|drift| drift.is_none())
+    }
+
+    /// Returns the `(expected, actual)` current working directories if the current working directory is not the
+    /// [expected](Self::get_expected()) one, e.g. when something else has changed the current working directory.
+    ///
+    /// The directories are compared in their canonical forms where possible.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`get()`](Self::get()).
+    #[inline]
+    pub fn drift(&self) -> io::Result<Option<(PathBuf, PathBuf)>> {
+        self.get().map(|actual| {
+            self.get_expected()
+                .filter(|expected| !is_same_path(expected, &actual))
+                .map(|expected| (expected, actual))
+        })
+    }
+
+    /// Returns the number of live [`CwdGuard`]s, including those nested under other [`CwdGuard`]s, borrowing this
+    /// [`Cwd`].
+    ///
+    /// As [`Cwd::mutex()`] is the only [`Cwd`] accessing the process' current working directory, this is generally
+    /// useful for finding leaked [`CwdGuard`]s.
+    #[inline]
+    #[must_use]
+    pub const fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Creates a [`CwdRef`] that can only read `self`, for sharing without allowing changes.
+    #[inline]
+    #[must_use]
+    pub const fn as_ref_readonly(&self) -> CwdRef<'_, C> {
+        CwdRef { cwd: self }
+    }
+
+    /// Wrapper function to ensure [`CwdProvider::current()`] (i.e. [`env::current_dir()`]) is called with the [`Cwd`] borrowed.
+    #[inline]
+    #[doc(alias = "current_dir")]
+    #[expect(clippy::missing_errors_doc, reason = "Wrapper function")]
+    pub fn get(&self) -> io::Result<PathBuf> {
+        self.assert_lock_held();
+        self.provider.current().inspect(|path| {
+            if self.track_expected {
+                clone_cell_value(&self.expected_cwd).map_or_else(
+                    || {
+                        self.expected_cwd
+                            .set(Some((path.clone(), ExpectationSource::Observed)));
+                    },
+                    |(expected, _)| self.notify_drift(&expected, path),
+                );
+            }
+        })
+    }
+
+    /// Returns the current working directory as per [`get()`](Self::get()) in an [`Arc`], to share between threads
+    /// without cloning the path for each.
+    ///
+    /// The path is a snapshot at the time of the call and does not follow later changes to the current working
+    /// directory.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`get()`](Self::get()).
+    #[inline]
+    pub fn current_dir_arc(&self) -> io::Result<Arc<Path>> {
+        self.get().map(Arc::from)
+    }
+
+    /// Calls the [drift handler](Self::on_drift()), if any, with `expected` and `actual` if they differ in their
+    /// canonical forms where possible.
+    fn notify_drift(&self, expected: &Path, actual: &Path) {
+        if let Some(mut drift_handler) = self.drift_handler.take() {
+            if !is_same_path(expected, actual) {
+                drift_handler(expected, actual);
+            }
+            self.drift_handler.set(Some(drift_handler));
+        }
+    }
+
+    /// Registers a `handler`, or removes it with [`None`], called with the `(expected, actual)` current working
+    /// directories whenever [`get()`](Self::get()) notices they differ while [tracking](Self::track_expected()),
+    /// including through [`is_expected()`](Self::is_expected()) and [`drift()`](Self::drift()).
+    ///
+    /// The directories are compared in their canonical forms where possible, but only while a handler is
+    /// registered. The handler cannot access `self`, it must not [`Cwd::lock()`] as the lock is already held.
+    #[inline]
+    pub fn on_drift(&mut self, handler: Option<DriftHandler>) {
+        *self.drift_handler.get_mut() = handler;
+    }
+
+    /// Reads the current working directory as per [`get()`](Self::get()) into `buf`, replacing its contents.
+    ///
+    /// This is intended for polling the current working directory, `buf`'s allocation is reused rather than
+    /// returning a new [`PathBuf`] for each call.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`get()`](Self::get()), leaving `buf` unchanged.
+    #[inline]
+    pub fn get_into(&self, buf: &mut PathBuf) -> io::Result<()> {
+        self.get().map(|path| {
+            buf.as_mut_os_string().clear();
+            buf.push(path);
+        })
+    }
+
+    /// Joins a relative `path` onto the current working directory and [lexically
+    /// normalizes](Cwd::lexically_normalize()) the result, returning an absolute `path` unchanged.
+    ///
+    /// The filesystem is only accessed to retrieve the current working directory, so `path` need not exist.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`get()`](Self::get()) for a relative `path`.
+    #[inline]
+    pub fn absolute<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        if path.as_ref().is_absolute() {
+            Ok(path.as_ref().to_path_buf())
+        } else {
+            self.get()
+                .map(|current_cwd| Cwd::lexically_normalize(current_cwd.join(path)))
+        }
+    }
+
+    /// Expresses `target` relative to the current working directory, with both in canonical form as per
+    /// [`canonical_get()`](Self::canonical_get()), using [`ParentDir`](Component::ParentDir)s as needed.
+    ///
+    /// A `target` that shares no root with the current working directory, e.g. on another drive, is returned as an
+    /// absolute path.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`canonical_get()`](Self::canonical_get()) or `target`
+    /// cannot be canonicalized as per [`fs::canonicalize()`].
+    #[inline]
+    pub fn relative_to<P: AsRef<Path>>(&self, target: P) -> io::Result<PathBuf> {
+        match (self.canonical_get(), fs::canonicalize(target)) {
+            (Ok(canonical_cwd), Ok(canonical_target)) => {
+                Ok(relative_path(&canonical_cwd, &canonical_target))
+            }
+            (Err(err), _) | (_, Err(err)) => Err(err),
+        }
+    }
+
+    /// Describes where the current working directory is relative to `expected`, with both in canonical form as per
+    /// [`canonical_get()`](Self::canonical_get()).
+    ///
+    /// This is generally useful for more descriptive test failures than comparing the paths.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`canonical_get()`](Self::canonical_get()) or `expected`
+    /// cannot be canonicalized as per [`fs::canonicalize()`].
+    #[inline]
+    pub fn diff_against<P: AsRef<Path>>(&self, expected: P) -> io::Result<PathDiff> {
+        match (self.canonical_get(), fs::canonicalize(expected)) {
+            (Ok(canonical_cwd), Ok(canonical_expected)) => {
+                Ok(if canonical_cwd == canonical_expected {
+                    PathDiff::Same
+                } else if let Ok(below) = canonical_cwd.strip_prefix(&canonical_expected) {
+                    PathDiff::Below(below.to_path_buf())
+                } else if let Ok(above) = canonical_expected.strip_prefix(&canonical_cwd) {
+                    PathDiff::Above(above.to_path_buf())
+                } else {
+                    PathDiff::Unrelated(relative_path(&canonical_expected, &canonical_cwd))
+                })
+            }
+            (Err(err), _) | (_, Err(err)) => Err(err),
+        }
+    }
+
+    /// Returns an owned snapshot of the current working directory, equivalent to [`get()`](Self::get()).
+    ///
+    /// This generally reads better where the current working directory is passed on, e.g. to [`fs`] functions.
+    /// ```rust
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    ///       use std::{env::temp_dir, fs::read_dir};
+    ///       use current_dir::*;
+    ///
+    ///       let mut locked_cwd = Cwd::mutex().lock()?;
+    ///       locked_cwd.set(temp_dir())?;
+    ///       let entries = read_dir(locked_cwd.current_path()?)?;
+    /// #     drop(entries);
+    /// #     drop(locked_cwd);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`get()`](Self::get()).
+    #[inline]
+    pub fn current_path(&self) -> io::Result<PathBuf> {
+        self.get()
+    }
+
+    /// Gets the current working directory as per [`get()`](Self::get()) with all symlinks resolved as per
+    /// [`fs::canonicalize()`].
+    ///
+    /// Unlike [`get()`](Self::get()), resolving the path accesses each of its components on the filesystem.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`get()`](Self::get()) or canonicalized as per
+    /// [`fs::canonicalize()`], e.g. the current directory has been removed or one of its components is inaccessible.
+    #[inline]
+    pub fn canonical_get(&self) -> io::Result<PathBuf> {
+        match self.get() {
+            Ok(path) => fs::canonicalize(path),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Gets the current working directory as per [`get()`](Self::get()) with its [`CurDir`](Component::CurDir) and
+    /// [`ParentDir`](Component::ParentDir) components resolved as per [`Cwd::lexically_normalize()`].
+    ///
+    /// Unlike [`canonical_get()`](Self::canonical_get()), the filesystem is not accessed, so this is cheaper and works
+    /// even if a component has been removed, but symlinks are not resolved.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`get()`](Self::get()).
+    #[inline]
+    pub fn normalize_get(&self) -> io::Result<PathBuf> {
+        self.get().map(Cwd::lexically_normalize)
+    }
+
+    /// Wrapper function to ensure [`CwdProvider::set()`] (i.e. [`env::set_current_dir()`]) is called with the [`Cwd`] borrowed.
+    ///
+    /// The [`CurDir`](Component::CurDir) components and trailing separators of `path` are removed first, so that
+    /// e.g. `./dir/` is set, and [tracked](Self::track_expected()), as `dir`.
+    ///
+    /// # Errors
+    /// The current directory cannot be set as per [`CwdProvider::set()`], `path` is outside the
+    /// [jail](Self::set_jail()) or cannot be canonicalized to check as much, or the current working directory is
+    /// [frozen](Self::freeze()), i.e. [`io::ErrorKind::PermissionDenied`].
+    ///
+    /// In [dry run](Self::set_dry_run()) mode, `path` is only recorded.
+    #[inline]
+    #[doc(alias = "set_current_dir")]
+    pub fn set<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.change_dir(path.as_ref(), true)
+    }
+
+    /// [`set()`](Self::set()) the current working directory to `path`, recording it in the
+    /// [`history()`](Self::history()) if `record_history`.
+    fn change_dir(&mut self, raw_path: &Path, record_history: bool) -> io::Result<()> {
+        self.assert_lock_held();
+        if self.frozen > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "cwd is frozen",
+            ));
+        }
+        let cleaned_path = strip_cur_dirs(raw_path);
+        let path = cleaned_path.as_path();
+        match self.check_jail(path) {
+            Ok(()) if self.dry_run => {
+                self.dry_run_log.push(path.to_path_buf());
+                Ok(())
+            }
+            Ok(()) => {
+                let previous_cwd = self.provider.current().ok();
+                let expected_path = self.track_expected.then(|| {
+                    self.provider
+                        .canonicalize(path)
+                        .unwrap_or_else(|_| path.to_path_buf())
+                });
+                self.provider.set(path).map(|()| {
+                    #[cfg(feature = "log")]
+                    log::debug!(
+                        target: "current_dir",
+                        "cwd: {} -> {}",
+                        previous_cwd
+                            .as_deref()
+                            .unwrap_or_else(|| Path::new("<unknown>"))
+                            .display(),
+                        path.display()
+                    );
+                    self.previous_cwd = previous_cwd;
+                    if record_history {
+                        self.record_history(path);
+                    }
+                    if let Some(expected) = expected_path {
+                        self.expected_cwd
+                            .set(Some((expected, ExpectationSource::Manual)));
+                    }
+                })
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Records the current working directory, or `path` if it cannot be retrieved, in the
+    /// [`history()`](Self::history()) if enabled, discarding any entries [undone](Self::undo()) and evicting the
+    /// oldest entry if it is full.
+    fn record_history(&mut self, path: &Path) {
+        if self.history_capacity > 0 {
+            let current_len = self.history.len().saturating_sub(self.history_undone);
+            self.history.truncate(current_len);
+            self.history_undone = 0;
+            if self.history.len() >= self.history_capacity {
+                self.history.pop_front();
+            }
+            let current_cwd = self
+                .provider
+                .current()
+                .unwrap_or_else(|_| path.to_path_buf());
+            self.history.push_back((Instant::now(), current_cwd));
+        }
+    }
+
+    /// Records up to `capacity` of the most recent changes to the current working directory by
+    /// [`set()`](Self::set()), and therefore [`CwdGuard::reset()`], in the [`history()`](Self::history()).
+    ///
+    /// The oldest entries are evicted once `capacity` is reached, or immediately if already exceeded. The history is
+    /// disabled by default and with a `capacity` of `0`, which also clears it.
+    #[inline]
+    pub fn enable_history(&mut self, capacity: usize) {
+        self.history_capacity = capacity;
+        let excess = self.history.len().saturating_sub(capacity);
+        self.history.drain(..excess);
+        self.history_undone = self
+            .history_undone
+            .min(self.history.len().saturating_sub(1));
+    }
+
+    /// [`set()`](Self::set()) the current working directory back to the previous entry of the
+    /// [`history()`](Self::history()), returning it, or [`None`] if there is none.
+    ///
+    /// The entries stepped back over are kept for [`redo()`](Self::redo()) until the next [`set()`](Self::set()),
+    /// which discards them.
+    ///
+    /// # Errors
+    /// The current directory cannot be set as per [`set()`](Self::set()), in which case the position in the history
+    /// is unchanged.
+    #[inline]
+    pub fn undo(&mut self) -> io::Result<Option<PathBuf>> {
+        let previous_index = self
+            .history
+            .len()
+            .checked_sub(self.history_undone.saturating_add(2));
+        previous_index.map_or(Ok(None), |index| {
+            self.change_to_history(index).map(|path| {
+                self.history_undone = self.history_undone.saturating_add(1);
+                Some(path)
+            })
+        })
+    }
+
+    /// [`set()`](Self::set()) the current working directory forward to the next entry of the
+    /// [`history()`](Self::history()) stepped back over by [`undo()`](Self::undo()), returning it, or [`None`] if
+    /// there is none.
+    ///
+    /// # Errors
+    /// The current directory cannot be set as per [`set()`](Self::set()), in which case the position in the history
+    /// is unchanged.
+    #[inline]
+    pub fn redo(&mut self) -> io::Result<Option<PathBuf>> {
+        match self.history_undone.checked_sub(1) {
+            Some(remaining_undone) => {
+                let next_index = self.history.len().saturating_sub(self.history_undone);
+                self.change_to_history(next_index).map(|path| {
+                    self.history_undone = remaining_undone;
+                    Some(path)
+                })
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Changes the current working directory to the `history` entry at `index` without recording it, returning it.
+    fn change_to_history(&mut self, index: usize) -> io::Result<PathBuf> {
+        let path = self
+            .history
+            .get(index)
+            .map(|entry| entry.1.clone())
+            .unwrap_or_default();
+        self.change_dir(&path, false).map(|()| path)
+    }
+
+    /// Returns when the current working directory was changed by [`set()`](Self::set()) and to what, oldest first, as
+    /// recorded since the [history was enabled](Self::enable_history()).
+    #[inline]
+    #[must_use]
+    pub fn history(&self) -> Vec<(Instant, PathBuf)> {
+        self.history.iter().cloned().collect()
+    }
+
+    /// [`set()`](Self::set()) the current working directory to the [absolute](Self::absolute()),
+    /// [lexically normalized](Cwd::lexically_normalize()) form of `path`, which becomes the
+    /// [expected current working directory](Self::get_expected()) regardless of [tracking](Self::track_expected()).
+    ///
+    /// Unlike setting `path` as is, the expectation is then comparable to [`get()`](Self::get()), barring symlinks.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved to make `path` absolute as per [`absolute()`](Self::absolute()) or
+    /// set as per [`set()`](Self::set()), in which case the expectation is unchanged.
+    #[inline]
+    pub fn set_normalized<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        match self.absolute(path) {
+            Ok(absolute_path) => {
+                let normalized = Cwd::lexically_normalize(absolute_path);
+                self.set(&normalized)
+                    .map(|()| self.set_expected(normalized))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// [`set()`](Self::set()) the current working directory to `path` like `cd -L`, preserving symlinks in the
+    /// [expected current working directory](Self::get_expected()).
+    ///
+    /// The logical path is `path` joined onto the [expected or current](Self::expected_or_current()) working
+    /// directory and [lexically normalized](Cwd::lexically_normalize()), so `link/..` is the directory containing
+    /// `link`. The current working directory is set to its [canonical](fs::canonicalize()) form and the logical path
+    /// becomes the expectation regardless of [tracking](Self::track_expected()).
+    ///
+    /// # Errors
+    /// The base directory cannot be retrieved as per [`expected_or_current()`](Self::expected_or_current()), the
+    /// logical path cannot be canonicalized as per [`fs::canonicalize()`] or set as per [`set()`](Self::set()), in
+    /// which case the expectation is unchanged.
+    #[inline]
+    pub fn set_logical<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        match self.expected_or_current() {
+            Ok(base) => {
+                let logical_path = Cwd::lexically_normalize(base.join(path));
+                match fs::canonicalize(&logical_path) {
+                    Ok(physical_path) => self
+                        .set(physical_path)
+                        .map(|()| self.set_expected(logical_path)),
+                    Err(err) => Err(err),
+                }
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// [`set()`](Self::set()) the current working directory to `path` like `cd -P`, resolving symlinks in the
+    /// [expected current working directory](Self::get_expected()).
+    ///
+    /// The current working directory is set to the [canonical](fs::canonicalize()) form of `path`, which becomes the
+    /// expectation regardless of [tracking](Self::track_expected()).
+    ///
+    /// # Errors
+    /// `path` cannot be canonicalized as per [`fs::canonicalize()`] or set as per [`set()`](Self::set()), in which
+    /// case the expectation is unchanged.
+    #[inline]
+    pub fn set_physical<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        match fs::canonicalize(path) {
+            Ok(physical_path) => self
+                .set(&physical_path)
+                .map(|()| self.set_expected(physical_path)),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates a [`FreezeGuard`] making [`set()`](Self::set()), and therefore [`CwdGuard::reset()`], fail with
+    /// [`io::ErrorKind::PermissionDenied`] until it is dropped, while [`get()`](Self::get()) still succeeds.
+    ///
+    /// Freezes nest, the current working directory is only unfrozen once every [`FreezeGuard`] has been dropped.
+    #[inline]
+    pub fn freeze(&mut self) -> FreezeGuard<'_, C> {
+        self.frozen = self.frozen.saturating_add(1);
+        FreezeGuard { cwd: self }
+    }
+
+    /// Creates a [`DeferredGuard`] that will [`set()`](Self::set()) the current working directory to `target` when
+    /// dropped, leaving it unchanged until then.
+    ///
+    /// Unlike a [`CwdGuard`], the current working directory is moved on to `target` rather than reset.
+    #[inline]
+    pub fn set_deferred(&mut self, target: PathBuf) -> DeferredGuard<'_, C> {
+        DeferredGuard { cwd: self, target }
+    }
+
+    /// Enables or disables dry run mode, where [`set()`](Self::set()), and therefore [`CwdGuard::reset()`], records
+    /// the paths it would set in the [`dry_run_log()`](Self::dry_run_log()) instead of setting them.
+    ///
+    /// The current working directory, as per [`get()`](Self::get()), is left unchanged while enabled.
+    #[inline]
+    pub fn set_dry_run(&mut self, enabled: bool) {
+        self.dry_run = enabled;
+    }
+
+    /// Returns the paths [`set()`](Self::set()) would have set while in [dry run](Self::set_dry_run()) mode, in the
+    /// order they were requested.
+    #[inline]
+    #[must_use]
+    pub fn dry_run_log(&self) -> &[PathBuf] {
+        &self.dry_run_log
+    }
+
+    /// Restricts [`set()`](Self::set()), and therefore [`CwdGuard::reset()`], to the canonical form of `root` and the
+    /// directories within it, or lifts the restriction with [`None`].
+    ///
+    /// Paths are compared in canonical form as per [`fs::canonicalize()`], so neither
+    /// [`ParentDir`](Component::ParentDir)s nor symlinks can escape the jail. Relative paths are resolved against
+    /// the current working directory of the process.
+    ///
+    /// # Errors
+    /// `root` cannot be canonicalized as per [`fs::canonicalize()`], leaving the jail unchanged.
+    #[inline]
+    pub fn set_jail(&mut self, root: Option<PathBuf>) -> io::Result<()> {
+        root.map(fs::canonicalize)
+            .transpose()
+            .map(|jail| self.jail = jail)
+    }
+
+    /// Checks `path` is within the [jail](Self::set_jail()), if any.
+    fn check_jail(&self, path: &Path) -> io::Result<()> {
+        self.jail
+            .as_ref()
+            .map_or(Ok(()), |jail| match fs::canonicalize(path) {
+                Ok(canonical_path) if canonical_path.starts_with(jail) => Ok(()),
+                Ok(_) => Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!(
+                        "{} is outside of the jail {}",
+                        path.display(),
+                        jail.display()
+                    ),
+                )),
+                Err(err) => Err(err),
+            })
+    }
+
+    /// Gets the current working directory as per [`get()`](Self::get()) as an [`OsString`], without assuming it is
+    /// UTF-8.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`get()`](Self::get()).
+    #[inline]
+    pub fn get_os(&self) -> io::Result<OsString> {
+        self.get().map(PathBuf::into_os_string)
+    }
+
+    /// [`set()`](Self::set()) the current working directory to the [`OsStr`] `path`, without assuming it is UTF-8.
+    ///
+    /// # Errors
+    /// The current directory cannot be set as per [`set()`](Self::set()).
+    #[inline]
+    pub fn set_os(&mut self, path: &OsStr) -> io::Result<()> {
+        self.set(path)
+    }
+
+    /// [`set()`](Self::set()) the current working directory back to what it was before the last
+    /// [`set()`](Self::set()), like `cd -`, returning the directory moved to if any.
+    ///
+    /// Calling this again toggles between the two directories. As [`CwdGuard::reset()`] is also a
+    /// [`set()`](Self::set()), this returns into the last dropped [`CwdGuard`]s directory.
+    ///
+    /// # Errors
+    /// The current directory cannot be set as per [`set()`](Self::set()).
+    #[inline]
+    pub fn previous(&mut self) -> io::Result<Option<PathBuf>> {
+        self.previous_cwd.clone().map_or(Ok(None), |previous_cwd| {
+            self.set(&previous_cwd).map(|()| Some(previous_cwd))
+        })
+    }
+
+    /// [`set()`](Self::set()) the current working directory to `base` joined with each of the `components` in turn.
+    ///
+    /// The `components` are joined as per [`PathBuf::push()`], so an absolute component replaces the path joined so
+    /// far, e.g. `set_joined("/base", ["a", "/b", "c"])` sets `/b/c`.
+    ///
+    /// # Errors
+    /// The current directory cannot be set as per [`set()`](Self::set()).
+    #[inline]
+    #[doc(alias = "join_components")]
+    pub fn set_joined<B: AsRef<Path>, I>(&mut self, base: B, components: I) -> io::Result<()>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<Path>,
+    {
+        let joined =
+            components
+                .into_iter()
+                .fold(base.as_ref().to_path_buf(), |mut joined, component| {
+                    joined.push(component);
+                    joined
+                });
+        self.set(joined)
+    }
+
+    /// [`set()`](Self::set()) the current working directory to `path`, retrying up to `retries` times after `delay`
+    /// while the error is transient, i.e. [`io::ErrorKind::WouldBlock`], [`io::ErrorKind::Interrupted`] or a stale
+    /// network filesystem handle (`ESTALE`) on Unix.
+    ///
+    /// # Errors
+    /// The current directory cannot be set as per [`set()`](Self::set()) with a permanent error, e.g.
+    /// [`io::ErrorKind::NotFound`], or a transient error once out of `retries`.
+    #[inline]
+    pub fn set_retry<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        retries: usize,
+        delay: Duration,
+    ) -> io::Result<()> {
+        let mut remaining = retries;
+        loop {
+            match self.set(&path) {
+                Err(err) if remaining > 0 && is_transient(&err) => {
+                    remaining = remaining.saturating_sub(1);
+                    thread::sleep(delay);
+                }
+                result => break result,
+            }
+        }
+    }
+
+    /// [`set()`](Self::set()) the current working directory to `path` as soon as it exists as per
+    /// [`Path::try_exists()`], checking every `poll` for up to `timeout`.
+    ///
+    /// The filesystem is checked directly regardless of the [`CwdProvider`].
+    ///
+    /// # Errors
+    /// `path` does not appear within `timeout`, i.e. [`io::ErrorKind::TimedOut`], its existence cannot be determined
+    /// as per [`Path::try_exists()`] or the current directory cannot be set as per [`set()`](Self::set()).
+    #[inline]
+    pub fn set_when_ready<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        timeout: Duration,
+        poll: Duration,
+    ) -> io::Result<()> {
+        let deadline = Instant::now().checked_add(timeout);
+        loop {
+            match path.as_ref().try_exists() {
+                Ok(true) => break self.set(&path),
+                Ok(false) => {
+                    let remaining = deadline.map_or(poll, |deadline_instant| {
+                        deadline_instant.saturating_duration_since(Instant::now())
+                    });
+                    if remaining.is_zero() {
+                        break Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            format!(
+                                "{} did not appear within {timeout:?}",
+                                path.as_ref().display()
+                            ),
+                        ));
+                    }
+                    thread::sleep(poll.min(remaining));
+                }
+                Err(err) => break Err(err),
+            }
+        }
+    }
+
+    /// [`set()`](Self::set()) the current working directory to `path` if it exists as per [`Path::try_exists()`],
+    /// returning whether it was set.
+    ///
+    /// # Errors
+    /// The existence of `path` cannot be determined, e.g. a parent directory is inaccessible, or the current
+    /// directory cannot be set as per [`set()`](Self::set()), e.g. `path` is not a directory.
+    #[inline]
+    pub fn set_if_exists<P: AsRef<Path>>(&mut self, path: P) -> io::Result<bool> {
+        match path.as_ref().try_exists() {
+            Ok(true) => self.set(path).map(|()| true),
+            Ok(false) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// [`set()`](Self::set()) the current working directory to `path`, telling a directory that exists but cannot be
+    /// entered apart from one that is missing.
+    ///
+    /// If the [`CwdProvider`] is denied entering `path` and it is a directory on the filesystem, it is reported as not
+    /// traversable, leaving the permissions of the current user to the [`CwdProvider`] rather than guessing from the
+    /// permission bits.
+    ///
+    /// # Errors
+    /// The current directory cannot be set as per [`set()`](Self::set()), e.g. `path` is missing, i.e.
+    /// [`io::ErrorKind::NotFound`], or it is a directory that is not traversable, i.e.
+    /// [`io::ErrorKind::PermissionDenied`].
+    #[inline]
+    pub fn set_checked<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        match self.set(&path) {
+            Err(err)
+                if err.kind() == io::ErrorKind::PermissionDenied
+                    && self.frozen == 0
+                    && self.check_jail(path.as_ref()).is_ok()
+                    && fs::metadata(&path).is_ok_and(|metadata| metadata.is_dir()) =>
+            {
+                Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("{} exists but is not traversable", path.as_ref().display()),
+                ))
+            }
+            result => result,
+        }
+    }
+
+    /// [`set()`](Self::set()) the current working directory to `primary`, or to `fallback` if `primary` is not
+    /// found, returning whichever was set.
+    ///
+    /// Only [`io::ErrorKind::NotFound`] falls back, e.g. a `primary` that exists but cannot be entered is an error.
+    ///
+    /// # Errors
+    /// The current directory cannot be set to `primary` for any reason other than [`io::ErrorKind::NotFound`], or
+    /// cannot be set to `fallback`, as per [`set()`](Self::set()).
+    #[inline]
+    pub fn set_with_fallback<P: AsRef<Path>, F: AsRef<Path>>(
+        &mut self,
+        primary: P,
+        fallback: F,
+    ) -> io::Result<PathBuf> {
+        match self.set(&primary) {
+            Ok(()) => Ok(primary.as_ref().to_path_buf()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => self
+                .set(&fallback)
+                .map(|()| fallback.as_ref().to_path_buf()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates `path` and any missing parents as per [`fs::create_dir_all()`] then [`set()`](Self::set()) the
+    /// current working directory to it.
+    ///
+    /// # Errors
+    /// The directory cannot be created, with the error from [`fs::create_dir_all()`] prefixed by "cannot create the
+    /// directory" and the current working directory unchanged, or set, with the error from [`set()`](Self::set())
+    /// prefixed by "cannot set the current working directory" and the directory left created, as per
+    /// [`CwdResultExt::cwd_context()`].
+    #[inline]
+    pub fn ensure_and_set<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        match fs::create_dir_all(&path).cwd_context("cannot create the directory") {
+            Ok(()) => self
+                .set(path)
+                .cwd_context("cannot set the current working directory"),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Watches the current working directory at the time of the call, calling `on_delete` each time it is reported
+    /// as removed or renamed until the returned [`WatchHandle`] is dropped.
+    ///
+    /// Later changes to the current working directory do not change the watched directory, which is watched through its
+    /// parent as the process' reference to its current working directory can delay reports on the directory itself.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`get()`](Self::get()) or watched as per
+    /// [`notify::Watcher::watch()`].
+    #[cfg(feature = "notify")]
+    #[inline]
+    pub fn watch_deletion<F: Fn() + Send + 'static>(
+        &self,
+        on_delete: F,
+    ) -> io::Result<WatchHandle> {
+        use notify::{event::ModifyKind, EventKind, RecursiveMode, Watcher as _};
+
+        match self.get() {
+            Ok(watched_dir) => {
+                let deleted_dir = watched_dir.clone();
+                notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                    if event.is_ok_and(|notified| {
+                        matches!(
+                            notified.kind,
+                            EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))
+                        ) && notified.paths.contains(&deleted_dir)
+                    }) {
+                        on_delete();
+                    }
+                })
+                .and_then(|mut watcher| {
+                    watcher
+                        .watch(
+                            watched_dir.parent().unwrap_or(&watched_dir),
+                            RecursiveMode::NonRecursive,
+                        )
+                        .map(|()| WatchHandle { watcher })
+                })
+                .map_err(io::Error::other)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Recreates and [`set()`](Self::set()) the current working directory to the
+    /// [expected current working directory](Self::get_expected()) if either the current directory cannot be
+    /// retrieved or the expected directory does not exist, returning whether this was needed.
+    ///
+    /// This is generally useful when recovering from a [`CwdGuard`] that panicked as its directory was removed.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`get()`](Self::get()) without an expectation to recreate,
+    /// or the expected directory cannot be created or set as per [`ensure_and_set()`](Self::ensure_and_set()).
+    #[inline]
+    pub fn recreate_if_missing(&mut self) -> io::Result<bool> {
+        let current_cwd = self.get();
+        match self.get_expected() {
+            Some(expected_cwd) if current_cwd.is_err() || !expected_cwd.is_dir() => {
+                self.ensure_and_set(expected_cwd).map(|()| true)
+            }
+            Some(_) => Ok(false),
+            None => current_cwd.map(|_| false),
+        }
+    }
+
+    /// Gets the current working directory as per [`get()`](Self::get()), or if it has been removed, i.e.
+    /// [`io::ErrorKind::NotFound`], recreates and [`set()`](Self::set()) the
+    /// [expected current working directory](Self::get_expected()) and returns it.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`get()`](Self::get()) other than being removed or without an
+    /// expectation to recreate, or the expected directory cannot be created or set as per
+    /// [`ensure_and_set()`](Self::ensure_and_set()).
+    #[inline]
+    pub fn get_or_recreate_expected(&mut self) -> io::Result<PathBuf> {
+        match self.get() {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                self.get_expected().map_or(Err(err), |expected_cwd| {
+                    self.ensure_and_set(&expected_cwd).map(|()| expected_cwd)
+                })
+            }
+            result => result,
+        }
+    }
+
+    /// [`set()`](Self::set()) the current working directory to the home directory, as per the `HOME` environment
+    /// variable or `USERPROFILE` on Windows, returning it.
+    ///
+    /// # Errors
+    /// The home directory is not set, i.e. [`io::ErrorKind::NotFound`], or cannot be set as per
+    /// [`set()`](Self::set()).
+    #[inline]
+    pub fn cd_home(&mut self) -> io::Result<PathBuf> {
+        let home = env::var_os("HOME").or_else(|| {
+            if cfg!(windows) {
+                env::var_os("USERPROFILE")
+            } else {
+                None
+            }
+        });
+        home.map(PathBuf::from).map_or_else(
+            || {
+                Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "the home directory is not set",
+                ))
+            },
+            |home_dir| self.set(&home_dir).map(|()| home_dir),
+        )
+    }
+
+    /// [`set()`](Self::set()) the current working directory to the value of the environment variable `var`,
+    /// returning it.
+    ///
+    /// # Errors
+    /// `var` is not set, i.e. [`io::ErrorKind::NotFound`], is set but empty, i.e. [`io::ErrorKind::InvalidInput`], or
+    /// the current directory cannot be set as per [`set()`](Self::set()).
+    #[inline]
+    pub fn set_from_env(&mut self, var: &str) -> io::Result<PathBuf> {
+        match env::var_os(var) {
+            Some(value) if value.is_empty() => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("the environment variable {var} is empty"),
+            )),
+            Some(value) => {
+                let path = PathBuf::from(value);
+                self.set(&path).map(|()| path)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("the environment variable {var} is not set"),
+            )),
+        }
+    }
+
+    /// [`set()`](Self::set()) the current working directory to the root of the current working directory, i.e. `/`
+    /// on Unix or the root of the drive on Windows, returning it.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`get()`](Self::get()) or set as per
+    /// [`set()`](Self::set()).
+    #[inline]
+    pub fn cd_root(&mut self) -> io::Result<PathBuf> {
+        match self.get() {
+            Ok(current_cwd) => {
+                let root: PathBuf = current_cwd
+                    .components()
+                    .take_while(|component| {
+                        matches!(component, Component::Prefix(_) | Component::RootDir)
+                    })
+                    .collect();
+                self.set(&root).map(|()| root)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// [`set()`](Self::set()) the current working directory to the top level of the enclosing git repository,
+    /// returning it, or [`None`] if not inside one.
+    ///
+    /// The top level is the nearest ancestor of the current working directory containing a `.git` directory, or a
+    /// `.git` file as used by worktrees and submodules. The filesystem is searched directly regardless of the
+    /// [`CwdProvider`].
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`get()`](Self::get()) or set as per
+    /// [`set()`](Self::set()).
+    #[inline]
+    pub fn cd_git_root(&mut self) -> io::Result<Option<PathBuf>> {
+        match self.get() {
+            Ok(current_cwd) => current_cwd
+                .ancestors()
+                .find(|ancestor| {
+                    let marker = ancestor.join(".git");
+                    marker.is_dir() || marker.is_file()
+                })
+                .map(Path::to_path_buf)
+                .map_or(Ok(None), |git_root| {
+                    self.set(&git_root).map(|()| Some(git_root))
+                }),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Calls `scope` and asserts the current working directory is the same afterwards, resetting it before panicking
+    /// otherwise.
+    ///
+    /// This is generally useful for finding tests that do not restore the current working directory, which is
+    /// available to other crates with the `test-support` feature.
+    ///
+    /// # Panics
+    /// If the current working directory cannot be retrieved or is changed by `scope`.
+    #[cfg(any(test, feature = "test-support"))]
+    #[inline]
+    #[expect(clippy::panic, reason = "asserting")]
+    pub fn assert_unchanged<T, F: FnOnce(&mut Self) -> T>(&mut self, scope: F) -> T {
+        match self.get() {
+            Ok(initial_cwd) => {
+                let result = scope(self);
+                match self.get() {
+                    Ok(final_cwd) if final_cwd == initial_cwd => result,
+                    final_cwd => {
+                        let reset = self.set(&initial_cwd);
+                        panic!(
+                            "current working directory changed from {} to {final_cwd:?}, reset: {reset:?}",
+                            initial_cwd.display()
+                        )
+                    }
+                }
+            }
+            Err(err) => panic!("current working directory could not be retrieved: {err}"),
+        }
+    }
+
+    /// Calls [`set()`](Self::set()) with each of the `paths` in turn.
+    ///
+    /// # Errors
+    /// The current directory cannot be set as per [`set()`](Self::set()), the remaining `paths` are not visited and
+    /// the current working directory is left at the last of the `paths` successfully set.
+    #[inline]
+    pub fn set_many<I: IntoIterator<Item = PathBuf>>(&mut self, paths: I) -> io::Result<()> {
+        paths.into_iter().try_for_each(|path| self.set(path))
+    }
+
+    /// Creates a [`CwdGuard`] that will [`reset()`](CwdGuard::reset()) to the current working directory at the time of
+    /// the call, forwarding to [`CwdGuard::try_from()`].
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    ///       use std::{env::temp_dir, fs::create_dir_all};
+    ///       use current_dir::*;
+    /// #
+    /// #     let test_dir = temp_dir().join("sub");
+    /// #     if !test_dir.exists() {
+    /// #         create_dir_all(&test_dir)?;
+    /// #     }
+    ///
+    ///       let mut locked_cwd = Cwd::mutex().lock()?;
+    ///       locked_cwd.set(temp_dir())?;
+    ///       {
+    ///           let mut cwd_guard = locked_cwd.guard()?;
+    ///           cwd_guard.set("sub")?;
+    ///           {
+    ///               let mut sub_cwd_guard = cwd_guard.sub_guard()?;
+    ///               sub_cwd_guard.set(temp_dir())?;
+    /// #             assert_eq!(sub_cwd_guard.get()?, temp_dir());
+    ///           }
+    ///           // cwd == /tmp/sub
+    /// #         assert_eq!(cwd_guard.get()?, temp_dir().join("sub"));
+    ///       }
+    ///       // cwd == /tmp
+    /// #     assert_eq!(locked_cwd.get()?, temp_dir());
+    /// #     drop(locked_cwd);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`CwdGuard::try_from()`].
+    #[inline]
+    pub fn guard(&mut self) -> io::Result<CwdGuard<'_, C>> {
+        CwdGuard::try_from(self)
+    }
+
+    /// Spawns `command` with its working directory set to `dir` as per [`Command::current_dir()`], resolving a
+    /// relative `dir` against the current working directory as per [`absolute()`](Self::absolute()).
+    ///
+    /// The current working directory of this process is unchanged, but as `self` is borrowed mutably for the spawn,
+    /// it cannot be changed through the [`Cwd::mutex()`] while the child inherits its environment.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved to resolve `dir` or `command` cannot be spawned as per
+    /// [`Command::spawn()`].
+    #[inline]
+    pub fn spawn_in<P: AsRef<Path>>(&mut self, dir: P, command: &mut Command) -> io::Result<Child> {
+        match self.absolute(dir) {
+            Ok(absolute_dir) => command.current_dir(absolute_dir).spawn(),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates a [`CwdGuard`] that has [`set()`](Self::set()) the current working directory to the
+    /// [canonical](fs::canonicalize()) form of `path`, so that [`get()`](Self::get()) within it is the same across
+    /// platforms.
+    ///
+    /// `path` is canonicalized against the filesystem directly regardless of the [`CwdProvider`].
+    ///
+    /// # Errors
+    /// `path` cannot be canonicalized as per [`fs::canonicalize()`], the current directory cannot be retrieved as per
+    /// [`CwdGuard::try_from()`] or set as per [`set()`](Self::set()).
+    #[inline]
+    pub fn scoped_canonical<P: AsRef<Path>>(&mut self, path: P) -> io::Result<CwdGuard<'_, C>> {
+        match fs::canonicalize(path) {
+            Ok(canonical_path) => match self.guard() {
+                Ok(mut cwd_guard) => cwd_guard.set(canonical_path).map(|()| cwd_guard),
+                Err(err) => Err(err),
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates a [`CwdGuard`] as per [`guard()`](Self::guard()) that has [`set()`](Self::set()) the current working
+    /// directory to `dir` only if `cond`, resetting on drop either way.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`CwdGuard::try_from()`] or, if `cond`, set as per
+    /// [`set()`](Self::set()).
+    #[inline]
+    pub fn scope_if<P: AsRef<Path>>(&mut self, cond: bool, dir: P) -> io::Result<CwdGuard<'_, C>> {
+        match self.guard() {
+            Ok(mut cwd_guard) if cond => cwd_guard.set(dir).map(|()| cwd_guard),
+            result => result,
+        }
+    }
+
+    /// Creates a [`CwdGuard`] as per [`guard()`](Self::guard()) named `label` in its [`Debug`](fmt::Debug) output,
+    /// which is otherwise unaffected.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`CwdGuard::try_from()`].
+    #[inline]
+    pub fn scope_labeled<L: Into<String>>(&mut self, label: L) -> io::Result<CwdGuard<'_, C>> {
+        self.guard().map(|mut cwd_guard| {
+            cwd_guard.label = Some(label.into());
+            cwd_guard
+        })
+    }
+
+    /// Creates a [`ScopedEnvCwd`] that has [`set()`](Self::set()) the current working directory to `dir` and each
+    /// of the environment `vars` in turn, restoring both when dropped.
+    ///
+    /// Environment variables, like the current working directory, are shared by the whole process, but are not
+    /// protected by the [`Cwd::mutex()`]. Reading or writing them from other threads during the scope races with it.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`CwdGuard::try_from()`] or set as per
+    /// [`set()`](Self::set()), in which case no environment variables are set.
+    ///
+    /// # Panics
+    /// If any of the `vars` cannot be set as per [`env::set_var()`], e.g. the key is empty or contains `=`.
+    #[inline]
+    pub fn scope_with_env<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        vars: Vec<(OsString, OsString)>,
+    ) -> io::Result<ScopedEnvCwd<'_, C>> {
+        match self.guard() {
+            Ok(mut cwd_guard) => cwd_guard.set(dir).map(|()| {
+                let previous_vars = vars
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let previous_value = env::var_os(&key);
+                        env::set_var(&key, value);
+                        (key, previous_value)
+                    })
+                    .collect();
+                ScopedEnvCwd {
+                    previous_vars,
+                    cwd_guard,
+                }
+            }),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates a [`ScopedCleanupCwd`] that has [`set()`](Self::set()) the current working directory to `dir` and
+    /// calls `cleanup` when dropped, before the current working directory is reset.
+    ///
+    /// `cleanup` is called while still in `dir`, so it may use paths relative to `dir`, including when the scope is
+    /// dropped by unwinding from a panic.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`CwdGuard::try_from()`] or set as per
+    /// [`set()`](Self::set()), in which case `cleanup` is not called.
+    #[inline]
+    pub fn scoped_with_cleanup<P: AsRef<Path>, F: FnOnce()>(
+        &mut self,
+        dir: P,
+        cleanup: F,
+    ) -> io::Result<ScopedCleanupCwd<'_, F, C>> {
+        match self.guard() {
+            Ok(mut cwd_guard) => cwd_guard.set(dir).map(|()| ScopedCleanupCwd {
+                cleanup: Some(cleanup),
+                cwd_guard,
+            }),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates a [`CwdGuard`] that has visited each of the `paths` in turn as per [`set_many()`](Self::set_many()).
+    ///
+    /// The current working directory is left at the last of the `paths` and will [`reset()`](CwdGuard::reset()) to
+    /// the current working directory at the time of the call when the [`CwdGuard`] is dropped, i.e. a scoped batch
+    /// of [`set()`](Self::set())s.
+    /// ```rust
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    ///       use std::path::{Path, PathBuf};
+    ///       use current_dir::*;
+    ///
+    ///       let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+    ///       {
+    ///           let mut cwd_guard = cwd.scope_many([PathBuf::from("dir1"), PathBuf::from("dir2")])?;
+    ///           assert_eq!(cwd_guard.get()?, Path::new("/mock/dir1/dir2"));
+    ///       }
+    ///       assert_eq!(cwd.get()?, Path::new("/mock"));
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`CwdGuard::try_from()`] or set as per
+    /// [`set_many()`](Self::set_many()), in which case the current working directory has already been reset.
+    #[inline]
+    #[doc(alias = "set_scoped_batch")]
+    pub fn scope_many<I: IntoIterator<Item = PathBuf>>(
+        &mut self,
+        paths: I,
+    ) -> io::Result<CwdGuard<'_, C>> {
+        match CwdGuard::try_from(self) {
+            Ok(mut cwd_guard) => cwd_guard.set_many(paths).map(|()| cwd_guard),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Calls `scope` with a [`CwdGuard`], returning its result once the [`CwdGuard`] has been dropped and the
+    /// current working directory [`reset()`](CwdGuard::reset()) to that at the time of the call.
+    ///
+    /// The reset occurs whether `scope` returns [`Ok`], [`Err`] or panics.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`CwdGuard::try_from()`], otherwise the result of `scope`.
+    ///
+    /// # Panics
+    /// If `scope` panics or the current directory cannot be [`reset()`](CwdGuard::reset()) as per
+    /// [`CwdGuard::drop()`].
+    #[inline]
+    pub fn with_scope<T, F: FnOnce(&mut CwdGuard<'_, C>) -> io::Result<T>>(
+        &mut self,
+        scope: F,
+    ) -> io::Result<T> {
+        match CwdGuard::try_from(self) {
+            Ok(mut cwd_guard) => {
+                let result = scope(&mut cwd_guard);
+                drop(cwd_guard);
+                result
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// [`set()`](Self::set()) the current working directory to `dir`, calls `scope` and resets the current working
+    /// directory to that at the time of the call, returning the value produced by `scope`.
+    ///
+    /// Unlike [`with_scope()`](Self::with_scope()), failing to reset is returned rather than panicking, the reset also
+    /// occurs if `scope` panics, as per [`CwdGuard::drop()`].
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved or set, i.e. [`CwdError::Setup`], `scope` fails, i.e.
+    /// [`CwdError::Scope`], or the current directory cannot be reset whether or not `scope` failed, i.e.
+    /// [`CwdError::Reset`].
+    ///
+    /// # Panics
+    /// If `scope` panics and the current directory cannot be [`reset()`](CwdGuard::reset()) as per
+    /// [`CwdGuard::drop()`].
+    #[inline]
+    pub fn with_dir_and_value<P, T, E, F>(&mut self, dir: P, scope: F) -> Result<T, CwdError<E>>
+    where
+        P: AsRef<Path>,
+        F: FnOnce() -> Result<T, E>,
+    {
+        match self.guard() {
+            Ok(mut cwd_guard) => match cwd_guard.set(dir) {
+                Ok(()) => {
+                    let value = scope();
+                    let reset = cwd_guard.reset();
+                    drop(PathBuf::from(cwd_guard));
+                    match (reset, value) {
+                        (Ok(()), Ok(scope_value)) => Ok(scope_value),
+                        (Ok(()), Err(scope_err)) => Err(CwdError::Scope(scope_err)),
+                        (Err(reset_err), scope_value) => {
+                            Err(CwdError::Reset(reset_err, scope_value.err()))
+                        }
+                    }
+                }
+                Err(err) => {
+                    drop(PathBuf::from(cwd_guard));
+                    Err(CwdError::Setup(err))
+                }
+            },
+            Err(err) => Err(CwdError::Setup(err)),
+        }
+    }
+
+    /// [`set()`](Self::set()) the current working directory to `dir` and calls `scope`, keeping the current working
+    /// directory if it succeeds, otherwise resetting it to that at the time of the call.
+    ///
+    /// The reset also occurs if `scope` panics, as per [`CwdGuard::drop()`].
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`CwdGuard::try_from()`] or set as per
+    /// [`set()`](Self::set()), in which case `scope` is not called, otherwise the result of `scope`.
+    ///
+    /// # Panics
+    /// If `scope` panics or fails and the current directory cannot be [`reset()`](CwdGuard::reset()) as per
+    /// [`CwdGuard::drop()`].
+    #[inline]
+    #[doc(alias = "scoped_revert_on_error")]
+    pub fn try_in<P, T, E, F>(&mut self, dir: P, scope: F) -> io::Result<Result<T, E>>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(&mut Self) -> Result<T, E>,
+    {
+        match self.guard() {
+            Ok(mut cwd_guard) => cwd_guard.set(dir).map(|()| {
+                let result = scope(&mut cwd_guard);
+                if result.is_ok() {
+                    drop(PathBuf::from(cwd_guard));
+                }
+                result
+            }),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates a new [`TempDir`](tempfile::TempDir) and a [`TempDirScope`] that has [`set()`](Self::set()) the current
+    /// working directory to it.
+    ///
+    /// The current working directory is reset before the directory is removed when the [`TempDirScope`] is dropped.
+    ///
+    /// # Errors
+    /// The temporary directory cannot be created as per [`tempfile::tempdir()`], the current directory cannot be
+    /// retrieved as per [`CwdGuard::try_from()`] or set as per [`set()`](Self::set()).
+    #[cfg(feature = "tempfile")]
+    #[inline]
+    pub fn temp_dir_scope(&mut self) -> io::Result<TempDirScope<'_, C>> {
+        match tempfile::tempdir() {
+            Ok(temp_dir) => match CwdGuard::try_from(self) {
+                Ok(mut cwd_guard) => cwd_guard.set(temp_dir.path()).map(|()| TempDirScope {
+                    cwd_guard,
+                    temp_dir,
+                }),
+                Err(err) => Err(err),
+            },
+            Err(err) => Err(err),
+        }
+    }
+}
+impl<C: fmt::Debug> fmt::Debug for Cwd<C> {
     #[inline]
     #[expect(clippy::min_ident_chars, reason = "Default paramater name")]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Cwd")
             .field("expected_cwd", &clone_cell_value(&self.expected_cwd))
+            .field("track_expected", &self.track_expected)
+            .field("depth", &self.depth)
+            .field("previous_cwd", &self.previous_cwd)
+            .field("jail", &self.jail)
+            .field("frozen", &self.frozen)
+            .field("dry_run", &self.dry_run)
+            .field("dry_run_log", &self.dry_run_log)
+            .field("history", &self.history)
+            .field("drift_handler", &{
+                let drift_handler = self.drift_handler.take();
+                let has_drift_handler = drift_handler.is_some();
+                self.drift_handler.set(drift_handler);
+                has_drift_handler
+            })
+            .field("verify_lock_held", &self.verify_lock_held)
+            .field("history_capacity", &self.history_capacity)
+            .field("history_undone", &self.history_undone)
+            .field("provider", &self.provider)
             .finish()
     }
 }
 
+/// A read-only reference to a [`Cwd`], created by [`Cwd::as_ref_readonly()`].
+///
+/// Only the methods of [`Cwd`] borrowing it immutably, e.g. [`Cwd::get()`] and [`Cwd::get_expected()`], are
+/// available.
+/// ```compile_fail
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+///       use std::env::temp_dir;
+///       use current_dir::*;
+///
+///       let mut locked_cwd = Cwd::mutex().lock()?;
+///       let cwd_ref = locked_cwd.as_ref_readonly();
+///       cwd_ref.set(temp_dir())?;
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct CwdRef<'cwd, C = SysProvider> {
+    /// The [`Cwd`] to read.
+    cwd: &'cwd Cwd<C>,
+}
+impl<C> Deref for CwdRef<'_, C> {
+    type Target = Cwd<C>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.cwd
+    }
+}
+
+/// Watches the directory captured by [`Cwd::watch_deletion()`] until dropped.
+#[cfg(feature = "notify")]
+#[derive(Debug)]
+pub struct WatchHandle {
+    /// The watcher, which stops watching when dropped.
+    #[expect(dead_code, reason = "held until dropped")]
+    watcher: notify::RecommendedWatcher,
+}
+
+/// The [`Cwd::mutex()`] acquired through [`Cwd::lock()`], [`Cwd::lock_owned()`], [`Cwd::try_lock()`] or
+/// [`Cwd::lock_with_backoff()`], released on [`drop()`](Self::drop()).
+pub struct LockedCwd {
+    /// The acquired lock.
+    locked_cwd: MutexGuard<'static, Cwd>,
+}
+impl Drop for LockedCwd {
+    #[inline]
+    fn drop(&mut self) {
+        HOLDS_CWD_LOCK.set(false);
+    }
+}
+impl Deref for LockedCwd {
+    type Target = Cwd;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.locked_cwd
+    }
+}
+impl DerefMut for LockedCwd {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.locked_cwd
+    }
+}
+
+/// The signals handled by [`Cwd::install_signal_restore()`], returned to emulating their default action on
+/// [`drop()`](Self::drop()).
+#[cfg(all(feature = "signal", unix))]
+#[must_use = "dropping the handle immediately stops handling the signals"]
+pub struct SignalRestoreHandle {
+    /// The handle closing the handled signals.
+    handle: Handle,
+    /// The handled signals, whose default action is resumed when dropped.
+    signals: Vec<i32>,
+    /// The background thread handling the signals, joined when dropped.
+    thread: Option<thread::JoinHandle<()>>,
+}
+#[cfg(all(feature = "signal", unix))]
+impl fmt::Debug for SignalRestoreHandle {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignalRestoreHandle")
+            .field("signals", &self.signals)
+            .field("closed", &self.handle.is_closed())
+            .finish_non_exhaustive()
+    }
+}
+#[cfg(all(feature = "signal", unix))]
+impl Drop for SignalRestoreHandle {
+    /// Resumes the default action of the signals and stops handling them, waiting for any signal being handled.
+    #[inline]
+    fn drop(&mut self) {
+        resume_signal_defaults(
+            &mut SIGNAL_DEFAULTS
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+            self.signals.iter(),
+        );
+        self.handle.close();
+        if let Some(thread) = self.thread.take() {
+            drop(thread.join());
+        }
+    }
+}
+
+/// The panic hook installed by [`Cwd::install_panic_restore_hook()`], restoring the previous hook on
+/// [`drop()`](Self::drop()).
+#[must_use = "dropping the handle immediately restores the previous panic hook"]
+pub struct PanicRestoreHook {
+    /// The hook replaced by [`Cwd::install_panic_restore_hook()`], shared with the installed hook.
+    previous_hook: Arc<PanicHook>,
+    /// Whether the installed hook resets the current working directory, shared with the installed hook.
+    resetting: Arc<AtomicBool>,
+    /// The address of the installed hook, to tell whether it is still installed.
+    installed_hook: usize,
+}
+impl Drop for PanicRestoreHook {
+    /// Restores the previous hook if the installed hook is still installed, otherwise stops it resetting the current
+    /// working directory and keeps the hook set since, unless the current thread is panicking, where hooks cannot be
+    /// changed.
+    #[inline]
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            self.resetting.store(false, Ordering::Release);
+            let current_hook = panic::take_hook();
+            if panic_hook_address(&current_hook) == self.installed_hook {
+                drop(current_hook);
+                let previous_hook =
+                    mem::replace(&mut self.previous_hook, Arc::new(Box::new(|_| {})));
+                if let Ok(hook) = Arc::try_unwrap(previous_hook) {
+                    panic::set_hook(hook);
+                }
+            } else {
+                panic::set_hook(current_hook);
+            }
+            PANIC_RESTORE_HOOK_INSTALLED.store(false, Ordering::Release);
+        }
+    }
+}
+
+/// The current working directory shared between async tasks, serialised by the same [`Cwd::mutex()`] as threads.
+///
+/// The current working directory is global to the process, so a task must not hold an [`AsyncCwdGuard`] across an
+/// `.await` that could run other tasks relying on it, [`AsyncCwd::run_in()`] instead holds the lock for the whole
+/// future.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct AsyncCwd;
+#[cfg(feature = "tokio")]
+impl AsyncCwd {
+    /// Acquires the async [`Cwd`] as an [`AsyncLockedCwd`], along with the [`Cwd::mutex()`], waiting without
+    /// blocking the task's thread until both are available.
+    ///
+    /// The async [`Cwd`] is held in its own [`tokio::sync::Mutex`], so its state, e.g. its
+    /// [jail](Cwd::set_jail()) and [expectation](Cwd::get_expected()), is separate from that of the
+    /// [`Cwd::mutex()`]. The [`Cwd::mutex()`] is held by a [blocking](tokio::task::spawn_blocking()) thread until the
+    /// [`AsyncLockedCwd`] is dropped, so it excludes threads using [`Cwd::lock()`] like any other lock, and is released
+    /// untouched if the returned future is cancelled.
+    ///
+    /// # Panics
+    /// If not called from a tokio runtime, or the blocking thread cannot acquire the [`Cwd::mutex()`], as per
+    /// [`tokio::task::spawn_blocking()`] and [`Cwd::lock()`].
+    #[inline]
+    #[expect(
+        clippy::panic,
+        reason = "the blocking thread only fails if the runtime is shutting down"
+    )]
+    pub async fn lock() -> AsyncLockedCwd {
+        let cwd = ASYNC_CWD_MUTEX.lock().await;
+        let (acquired_sender, acquired_receiver) = oneshot::channel();
+        let (release_sender, release_receiver) = oneshot::channel::<()>();
+        drop(task::spawn_blocking(move || {
+            let locked_cwd = Cwd::lock();
+            if acquired_sender.send(()).is_ok() {
+                drop(release_receiver.blocking_recv());
+            }
+            drop(locked_cwd);
+        }));
+        acquired_receiver.await.map_or_else(
+            |err| panic!("the blocking thread acquiring the cwd lock stopped: {err}"),
+            |()| AsyncLockedCwd {
+                cwd,
+                release_sender,
+            },
+        )
+    }
+
+    /// Runs `future` with the current working directory set to `path`, holding the [`Cwd::mutex()`] through
+    /// [`AsyncCwd::lock()`] until it completes and resetting the current working directory afterwards.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved or set as per [`AsyncCwdGuard::new()`], in which case `future` is
+    /// not run.
+    ///
+    /// # Panics
+    /// If the lock cannot be acquired as per [`AsyncCwd::lock()`] or the current directory cannot be reset as per
+    /// [`CwdGuard::drop()`].
+    #[inline]
+    #[expect(
+        clippy::significant_drop_tightening,
+        reason = "the lock is held for the whole future"
+    )]
+    pub async fn run_in<P: AsRef<Path>, F: Future>(path: P, future: F) -> io::Result<F::Output> {
+        let mut locked_cwd = Self::lock().await;
+        let new_cwd_guard = AsyncCwdGuard::new(&mut locked_cwd, path);
+        match new_cwd_guard {
+            Ok(async_cwd_guard) => {
+                let output = future.await;
+                drop(async_cwd_guard);
+                Ok(output)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// The async [`Cwd`] and the [`Cwd::mutex()`] acquired through [`AsyncCwd::lock()`], released on drop.
+///
+/// Unlike a [`LockedCwd`], it can be held across an `.await` and sent between threads.
+#[cfg(feature = "tokio")]
+#[must_use = "dropping the guard immediately releases the cwd lock"]
+pub struct AsyncLockedCwd {
+    /// The async [`Cwd`].
+    cwd: AsyncMutexGuard<'static, Cwd>,
+    /// Releases the [`Cwd::mutex()`] held by the blocking thread when dropped.
+    #[expect(dead_code, reason = "held until dropped")]
+    release_sender: oneshot::Sender<()>,
+}
+#[cfg(feature = "tokio")]
+impl Deref for AsyncLockedCwd {
+    type Target = Cwd;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.cwd
+    }
+}
+#[cfg(feature = "tokio")]
+impl DerefMut for AsyncLockedCwd {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cwd
+    }
+}
+
+/// A [`CwdGuard`] for the [`AsyncLockedCwd`] that has set the current working directory on creation and resets it
+/// on drop.
+///
+/// It must not be held across an `.await` that could run other tasks relying on the current working directory, see
+/// [`AsyncCwd::run_in()`].
+#[cfg(feature = "tokio")]
+#[must_use = "dropping the guard immediately resets the cwd"]
+pub struct AsyncCwdGuard<'lock> {
+    /// The guard resetting the current working directory.
+    cwd_guard: CwdGuard<'lock>,
+}
+#[cfg(feature = "tokio")]
+impl<'lock> AsyncCwdGuard<'lock> {
+    /// Creates a [`CwdGuard`] from the [`AsyncLockedCwd`] and sets the current working directory to `path`.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`CwdGuard::try_from()`] or set as per [`Cwd::set()`].
+    #[inline]
+    pub fn new<P: AsRef<Path>>(locked_cwd: &'lock mut Cwd, path: P) -> io::Result<Self> {
+        match CwdGuard::try_from(locked_cwd) {
+            Ok(mut cwd_guard) => cwd_guard.set(path).map(|()| Self { cwd_guard }),
+            Err(err) => Err(err),
+        }
+    }
+}
+#[cfg(feature = "tokio")]
+impl<'lock> Deref for AsyncCwdGuard<'lock> {
+    type Target = CwdGuard<'lock>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.cwd_guard
+    }
+}
+#[cfg(feature = "tokio")]
+impl DerefMut for AsyncCwdGuard<'_> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cwd_guard
+    }
+}
+
+#[cfg(test)]
+mod provider_tests {
+    use super::*;
+
+    #[test]
+    fn mock_get_set() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+
+        cwd.set("dir1").unwrap();
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock/dir1"));
+
+        cwd.set("/other").unwrap();
+        assert_eq!(cwd.get().unwrap(), Path::new("/other"));
+    }
+
+    #[test]
+    fn mock_guard_recursive() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        {
+            let mut cwd_guard = CwdGuard::try_from(&mut cwd).unwrap();
+            cwd_guard.set("dir1").unwrap();
+            assert_eq!(cwd_guard.get().unwrap(), Path::new("/mock/dir1"));
+            {
+                let mut sub_cwd_guard = CwdGuard::try_from(&mut cwd_guard).unwrap();
+                sub_cwd_guard.set("dir2").unwrap();
+                assert_eq!(sub_cwd_guard.get().unwrap(), Path::new("/mock/dir1/dir2"));
+            }
+            assert_eq!(cwd_guard.get().unwrap(), Path::new("/mock/dir1"));
+        }
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+    }
+
+    #[test]
+    fn guard_depth() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        assert_eq!(cwd.depth(), 0);
+        {
+            let mut cwd_guard = cwd.guard().unwrap();
+            assert_eq!(cwd_guard.depth(), 1);
+            {
+                let mut sub_cwd_guard = cwd_guard.sub_guard().unwrap();
+                let sub_sub_cwd_guard = sub_cwd_guard.sub_guard().unwrap();
+                assert_eq!(sub_sub_cwd_guard.depth(), 3);
+            }
+            assert_eq!(cwd_guard.depth(), 1);
+        }
+        assert_eq!(cwd.depth(), 0);
+    }
+
+    #[test]
+    fn guard_into_initial_cwd() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        let mut cwd_guard = cwd.scope_labeled("kept").unwrap();
+        cwd_guard.set("dir1").unwrap();
+        let initial_cwd: PathBuf = cwd_guard.into();
+        assert_eq!(initial_cwd, Path::new("/mock"));
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock/dir1"));
+        assert_eq!(cwd.depth(), 0);
+    }
+
+    #[test]
+    fn guard_reset_to() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        {
+            let mut cwd_guard = cwd.guard().unwrap();
+            cwd_guard.set("/dir1").unwrap();
+            cwd_guard.reset_to("dir2").unwrap();
+            assert_eq!(cwd_guard.get().unwrap(), Path::new("/dir1/dir2"));
+
+            cwd_guard.set("/dir3").unwrap();
+            cwd_guard.reset().unwrap();
+            assert_eq!(cwd_guard.get().unwrap(), Path::new("/dir1/dir2"));
+            cwd_guard.set("/dir3").unwrap();
+        }
+        assert_eq!(cwd.get().unwrap(), Path::new("/dir1/dir2"));
+    }
+
+    #[test]
+    fn freeze() {
+        use std::io::ErrorKind;
+
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        {
+            let mut frozen_cwd = cwd.freeze();
+            let frozen_err = frozen_cwd.set("/dir1").unwrap_err();
+            assert_eq!(frozen_err.kind(), ErrorKind::PermissionDenied);
+            assert_eq!(frozen_err.to_string(), "cwd is frozen");
+            assert_eq!(frozen_cwd.get().unwrap(), Path::new("/mock"));
+            {
+                let mut refrozen_cwd = frozen_cwd.freeze();
+                refrozen_cwd.set("/dir1").unwrap_err();
+            }
+            frozen_cwd.set("/dir1").unwrap_err();
+        }
+        cwd.set("/dir1").unwrap();
+        assert_eq!(cwd.get().unwrap(), Path::new("/dir1"));
+    }
+
+    #[test]
+    fn set_strips_cur_dirs() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        cwd.track_expected(true);
+        cwd.set("./dir1/./").unwrap();
+        assert_eq!(
+            cwd.try_clone_expected().unwrap().as_os_str(),
+            Path::new("/mock/dir1").as_os_str()
+        );
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock/dir1"));
+
+        cwd.set(".").unwrap();
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock/dir1"));
+    }
+
+    #[test]
+    fn mock_canonical_expected() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        cwd.track_expected(true);
+        cwd.set("dir1/../dir2").unwrap();
+        assert_eq!(cwd.try_clone_expected().unwrap(), Path::new("/mock/dir2"));
+        cwd.set("/other").unwrap();
+        assert_eq!(cwd.try_clone_expected().unwrap(), Path::new("/other"));
+    }
+
+    #[test]
+    fn try_clone_expected() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        cwd.track_expected(true);
+        assert_eq!(cwd.try_clone_expected(), None);
+        assert_eq!(cwd.get_expected().unwrap(), Path::new("/mock"));
+        assert_eq!(cwd.try_clone_expected().unwrap(), Path::new("/mock"));
+
+        cwd.set_expected("/dir1");
+        assert_eq!(cwd.try_clone_expected().unwrap(), Path::new("/dir1"));
+    }
+
+    #[test]
+    fn guard_debug() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        let mut cwd_guard = cwd.guard().unwrap();
+        cwd_guard.set("dir1").unwrap();
+        cwd_guard.track_expected(true);
+        let expected_cwd = cwd_guard.try_clone_expected();
+        let debug = format!("{cwd_guard:?}");
+        assert_eq!(cwd_guard.try_clone_expected(), expected_cwd);
+        assert!(debug.contains("label: None"));
+        assert!(debug.contains(&format!("initial_cwd: {:?}", Path::new("/mock"))));
+        assert!(debug.contains(&format!("current_cwd: Ok({:?})", Path::new("/mock/dir1"))));
+    }
+
+    #[test]
+    fn scope_if() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        {
+            let mut cwd_guard = cwd.scope_if(false, "/dir1").unwrap();
+            assert_eq!(cwd_guard.get().unwrap(), Path::new("/mock"));
+            cwd_guard.set("/dir2").unwrap();
+        }
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+        {
+            let cwd_guard = cwd.scope_if(true, "/dir1").unwrap();
+            assert_eq!(cwd_guard.get().unwrap(), Path::new("/dir1"));
+        }
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+    }
+
+    #[test]
+    fn scope_labeled() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        let mut cwd_guard = cwd.scope_labeled("build").unwrap();
+        cwd_guard.set("dir1").unwrap();
+        let sub_cwd_guard = cwd_guard.sub_guard().unwrap();
+        assert!(format!("{sub_cwd_guard:?}").contains("label: None"));
+        drop(sub_cwd_guard);
+        assert!(format!("{cwd_guard:?}").contains(r#"label: Some("build")"#));
+        drop(cwd_guard);
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(loom))]
+mod cwd_tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn get_into() {
+        let test_dir = test_dir!("dir1");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            let mut buf = PathBuf::new();
+            cwd.set(test_dir.join("dir1")).unwrap();
+            cwd.get_into(&mut buf).unwrap();
+            assert_eq!(buf, test_dir.join("dir1"));
+
+            cwd.set(&*test_dir).unwrap();
+            cwd.get_into(&mut buf).unwrap();
+            assert_eq!(buf, *test_dir);
+        });
+    }
+
+    #[test]
+    fn drift() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        assert!(cwd.is_expected().unwrap());
+        assert_eq!(cwd.drift().unwrap(), None);
+
+        cwd.set_expected("/mock");
+        assert!(cwd.is_expected().unwrap());
+        assert_eq!(cwd.drift().unwrap(), None);
+
+        // unexpected set
+        cwd.provider.set(Path::new("/other")).unwrap();
+        assert!(!cwd.is_expected().unwrap());
+        assert_eq!(
+            cwd.drift().unwrap(),
+            Some(("/mock".into(), "/other".into()))
+        );
+    }
+
+    #[test]
+    fn track_expected() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+
+        cwd.track_expected(false);
+        cwd.set("/mock/dir1").unwrap();
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock/dir1"));
+        assert_eq!(cwd.get_expected(), None);
+
+        cwd.track_expected(true);
+        assert_eq!(cwd.get_expected(), Some("/mock/dir1".into()));
+        cwd.set("/mock/dir2").unwrap();
+        assert_eq!(cwd.get_expected(), Some("/mock/dir2".into()));
+
+        // existing expectations are kept
+        cwd.track_expected(false);
+        cwd.set("/mock/dir3").unwrap();
+        assert_eq!(cwd.get_expected(), Some("/mock/dir2".into()));
+    }
+
+    #[test]
+    fn expected_or_current() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        cwd.track_expected(false);
+        assert_eq!(cwd.expected_or_current().unwrap(), Path::new("/mock"));
+        cwd.set_expected("/expected");
+        assert_eq!(cwd.expected_or_current().unwrap(), Path::new("/expected"));
+
+        cwd.clear_expected();
+        cwd.track_expected(true);
+        assert_eq!(cwd.expected_or_current().unwrap(), Path::new("/mock"));
+        assert_eq!(clone_cell_value(&cwd.expected_cwd), None);
+        cwd.set("/dir1").unwrap();
+        cwd.provider.cwd = PathBuf::from("/dir2");
+        assert_eq!(cwd.expected_or_current().unwrap(), Path::new("/dir1"));
+    }
+
+    #[test]
+    fn on_drift() {
+        use std::sync::mpsc;
+
+        let (sender, receiver) = mpsc::channel();
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        cwd.track_expected(true);
+        cwd.on_drift(Some(Box::new(move |expected: &Path, actual: &Path| {
+            sender
+                .send((expected.to_path_buf(), actual.to_path_buf()))
+                .unwrap();
+        })));
+        cwd.set("/dir1").unwrap();
+        cwd.get().unwrap();
+        assert_eq!(receiver.try_iter().count(), 0);
+
+        // changed without `Cwd::set()`
+        cwd.provider.cwd = PathBuf::from("/dir2");
+        cwd.get().unwrap();
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            [(PathBuf::from("/dir1"), PathBuf::from("/dir2"))]
+        );
+
+        cwd.on_drift(None);
+        assert!(!cwd.is_expected().unwrap());
+        assert_eq!(receiver.try_iter().count(), 0);
+    }
+
+    #[test]
+    fn set_clear_expected() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+
+        cwd.track_expected(false);
+        cwd.set_expected("/expected");
+        assert_eq!(cwd.get_expected(), Some("/expected".into()));
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+        cwd.clear_expected();
+        assert_eq!(cwd.get_expected(), None);
+
+        cwd.track_expected(true);
+        cwd.set_expected("/expected");
+        assert_eq!(cwd.get_expected(), Some("/expected".into()));
+        cwd.clear_expected();
+        assert_eq!(cwd.get_expected(), Some("/mock".into()));
+    }
+
+    #[test]
+    fn expected_with_source() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+
+        cwd.track_expected(false);
+        assert_eq!(cwd.expected_with_source(), None);
+        cwd.set_expected("/expected");
+        assert_eq!(
+            cwd.expected_with_source(),
+            Some(("/expected".into(), ExpectationSource::Manual))
+        );
+        cwd.clear_expected();
+
+        cwd.track_expected(true);
+        assert_eq!(
+            cwd.expected_with_source(),
+            Some(("/mock".into(), ExpectationSource::Observed))
+        );
+        cwd.set("/mock/dir1").unwrap();
+        assert_eq!(
+            cwd.expected_with_source(),
+            Some(("/mock/dir1".into(), ExpectationSource::Manual))
+        );
+    }
+
+    #[test]
+    fn set_many() {
+        let test_dir = test_dir!("dir1/dir2");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            cwd.set_many([(*test_dir).clone(), "dir1".into(), "dir2".into()])
+                .unwrap();
+            assert_eq!(cwd.get().unwrap(), test_dir.join("dir1/dir2"));
+        });
+    }
+
+    #[test]
+    fn set_many_mid_sequence_failure() {
+        let test_dir = test_dir!("dir1/dir2");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            let err = cwd
+                .set_many([
+                    (*test_dir).clone(),
+                    "dir1".into(),
+                    "missing".into(),
+                    "dir2".into(),
+                ])
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+            assert_eq!(cwd.get().unwrap(), test_dir.join("dir1"));
+        });
+    }
+
+    #[test]
+    fn scope_many() {
+        let test_dir = test_dir!("dir1/dir2");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+            let initial_cwd = cwd.get().unwrap();
+
+            {
+                let cwd_guard = cwd
+                    .scope_many([(*test_dir).clone(), "dir1".into(), "dir2".into()])
+                    .unwrap();
+                assert_eq!(cwd_guard.get().unwrap(), test_dir.join("dir1/dir2"));
+            }
+            assert_eq!(cwd.get().unwrap(), initial_cwd);
+
+            assert_eq!(
+                cwd.scope_many([(*test_dir).clone(), "missing".into()])
+                    .err()
+                    .map(|err| err.kind()),
+                Some(ErrorKind::NotFound)
+            );
+            assert_eq!(cwd.get().unwrap(), initial_cwd);
+        });
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn canonical_get() {
+        use std::os::unix::fs::symlink;
+
+        let test_dir = test_dir!("dir1");
+        symlink(test_dir.join("dir1"), test_dir.join("link")).unwrap();
+
+        let link_cwd = Cwd::with_provider(MockProvider::new(test_dir.join("link")));
+        assert_eq!(link_cwd.get().unwrap(), test_dir.join("link"));
+        assert_eq!(
+            link_cwd.canonical_get().unwrap(),
+            fs::canonicalize(test_dir.join("dir1")).unwrap()
+        );
+
+        let missing_cwd = Cwd::with_provider(MockProvider::new(test_dir.join("missing")));
+        assert_eq!(
+            missing_cwd.canonical_get().err().map(|err| err.kind()),
+            Some(ErrorKind::NotFound)
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn set_logical_physical() {
+        use std::os::unix::fs::symlink;
+
+        let test_dir = test_dir!("real/sub");
+        symlink(test_dir.join("real"), test_dir.join("link")).unwrap();
+        let mut cwd = Cwd::with_provider(MockProvider::new(fs::canonicalize(&*test_dir).unwrap()));
+        cwd.track_expected(false);
+        cwd.clear_expected();
+        let canonical_dir = cwd.get().unwrap();
+
+        cwd.set_logical("link/sub").unwrap();
+        assert_eq!(cwd.get_expected(), Some(canonical_dir.join("link/sub")));
+        assert_eq!(cwd.get().unwrap(), canonical_dir.join("real/sub"));
+
+        cwd.set_logical("..").unwrap();
+        assert_eq!(cwd.get_expected(), Some(canonical_dir.join("link")));
+        assert_eq!(cwd.get().unwrap(), canonical_dir.join("real"));
+
+        cwd.set_physical(canonical_dir.join("link/sub")).unwrap();
+        assert_eq!(cwd.get_expected(), Some(canonical_dir.join("real/sub")));
+        assert_eq!(cwd.get().unwrap(), canonical_dir.join("real/sub"));
+
+        cwd.set_logical("missing").unwrap_err();
+        assert_eq!(cwd.get_expected(), Some(canonical_dir.join("real/sub")));
+    }
+
+    #[test]
+    fn diff_against() {
+        let test_dir = test_dir!("dir1/dir2");
+        fs::create_dir_all(test_dir.join("dir3")).unwrap();
+        let canonical_dir = fs::canonicalize(&*test_dir).unwrap();
+        let mut cwd = Cwd::with_provider(MockProvider::new(canonical_dir.join("dir1")));
+
+        assert_eq!(
+            cwd.diff_against(test_dir.join("dir1")).unwrap(),
+            PathDiff::Same
+        );
+        assert_eq!(
+            cwd.diff_against(&*test_dir).unwrap(),
+            PathDiff::Below("dir1".into())
+        );
+        assert_eq!(
+            cwd.diff_against(test_dir.join("dir1/dir2")).unwrap(),
+            PathDiff::Above("dir2".into())
+        );
+        assert_eq!(
+            cwd.diff_against(test_dir.join("dir3")).unwrap(),
+            PathDiff::Unrelated(Path::new("..").join("dir1"))
+        );
+        assert_eq!(
+            cwd.diff_against(test_dir.join("missing"))
+                .err()
+                .map(|err| err.kind()),
+            Some(ErrorKind::NotFound)
+        );
+
+        cwd.set(canonical_dir.join("missing")).unwrap();
+        cwd.diff_against(&*test_dir).unwrap_err();
+    }
+
+    #[test]
+    fn absolute() {
+        let cwd = Cwd::with_provider(MockProvider::new("/mock/dir1"));
+        let absolute_path = cwd.get().unwrap().join("../missing");
+        assert_eq!(cwd.absolute(&absolute_path).unwrap(), absolute_path);
+        assert_eq!(
+            cwd.absolute("./missing").unwrap(),
+            Path::new("/mock/dir1/missing")
+        );
+        assert_eq!(
+            cwd.absolute("../missing/./dir2").unwrap(),
+            Path::new("/mock/missing/dir2")
+        );
+    }
+
+    #[test]
+    fn relative_to() {
+        let test_dir = test_dir!("dir1/dir2");
+        fs::create_dir_all(test_dir.join("sibling")).unwrap();
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            cwd.set(test_dir.join("dir1")).unwrap();
+            assert_eq!(
+                cwd.relative_to(test_dir.join("dir1/dir2")).unwrap(),
+                Path::new("dir2")
+            );
+            assert_eq!(cwd.relative_to("dir2/..").unwrap(), Path::new("."));
+            assert_eq!(
+                cwd.relative_to(test_dir.join("sibling")).unwrap(),
+                Path::new("../sibling")
+            );
+            cwd.set(test_dir.join("dir1/dir2")).unwrap();
+            assert_eq!(
+                cwd.relative_to(test_dir.join("sibling")).unwrap(),
+                Path::new("../../sibling")
+            );
+            assert_eq!(
+                cwd.relative_to(test_dir.join("missing"))
+                    .err()
+                    .map(|err| err.kind()),
+                Some(ErrorKind::NotFound)
+            );
+        });
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn relative_path_prefix() {
+        assert_eq!(
+            relative_path(Path::new(r"C:\a"), Path::new(r"C:\b")),
+            Path::new(r"..\b")
+        );
+        assert_eq!(
+            relative_path(Path::new(r"C:\a"), Path::new(r"D:\b")),
+            Path::new(r"D:\b")
+        );
+    }
+
+    #[test]
+    fn is_same_dir() {
+        let test_dir = test_dir!("dir1/dir2");
+        fs::create_dir_all(test_dir.join("other")).unwrap();
+        let dir1 = test_dir.join("dir1");
+
+        assert!(Cwd::is_same_dir(&dir1, test_dir.join("dir1/")).unwrap());
+        assert!(Cwd::is_same_dir(&dir1, test_dir.join("dir1/dir2/..")).unwrap());
+        assert!(!Cwd::is_same_dir(&dir1, test_dir.join("other")).unwrap());
+        assert_eq!(
+            Cwd::is_same_dir(&dir1, test_dir.join("missing"))
+                .err()
+                .map(|err| err.kind()),
+            Some(ErrorKind::NotFound)
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::symlink;
+
+            symlink(&dir1, test_dir.join("link")).unwrap();
+            assert!(Cwd::is_same_dir(&dir1, test_dir.join("link")).unwrap());
+        }
+    }
+
+    #[test]
+    fn lexically_normalize() {
+        assert_eq!(Cwd::lexically_normalize("a/b/../c"), Path::new("a/c"));
+        assert_eq!(Cwd::lexically_normalize("./a/./b/."), Path::new("a/b"));
+        assert_eq!(Cwd::lexically_normalize("../../a/.."), Path::new("../.."));
+        assert_eq!(Cwd::lexically_normalize("a/../.."), Path::new(".."));
+        assert_eq!(Cwd::lexically_normalize("a/.."), Path::new("."));
+        assert_eq!(Cwd::lexically_normalize("/../a/../../b"), Path::new("/b"));
+        assert_eq!(Cwd::lexically_normalize(""), Path::new("."));
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn lexically_normalize_prefix() {
+        assert_eq!(
+            Cwd::lexically_normalize(r"C:\..\a\.\b\..\c"),
+            Path::new(r"C:\a\c")
+        );
+        assert_eq!(Cwd::lexically_normalize(r"C:..\a"), Path::new(r"C:..\a"));
+        assert_eq!(
+            Cwd::lexically_normalize(r"\\server\share\..\a"),
+            Path::new(r"\\server\share\a")
+        );
+    }
+
+    #[test]
+    #[expect(clippy::panic, reason = "poisoning the lock")]
+    fn try_lock() {
+        mutex_test!(Cwd::mutex(), |locked_cwd| {
+            assert_eq!(
+                thread!(|| Cwd::try_lock().err()).unwrap(),
+                Some(TryLockKind::WouldBlock)
+            );
+            drop(locked_cwd);
+
+            thread!(|| {
+                let _locked_cwd = Cwd::try_lock().unwrap();
+                panic!("poisoning the Cwd lock");
+            })
+            .expect_err("panicked");
+            assert!(Cwd::mutex().is_poisoned());
+            drop(Cwd::try_lock().unwrap());
+            Cwd::mutex().clear_poison();
+        });
+    }
+
+    #[test]
+    fn lock_with_backoff() {
+        mutex_test!(Cwd::mutex(), |locked_cwd| {
+            let start = Instant::now();
+            assert!(Cwd::lock_with_backoff(Duration::from_millis(20)).is_none());
+            assert!(start.elapsed() >= Duration::from_millis(20));
+            drop(locked_cwd);
+
+            assert!(Cwd::lock_with_backoff(Duration::from_secs(10)).is_some());
+        });
+    }
+
+    #[test]
+    #[expect(clippy::panic, reason = "testing the panic hook")]
+    fn install_panic_restore_hook() {
+        let test_dir = test_dir!();
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let initial_cwd = locked_cwd.get().unwrap();
+            locked_cwd.track_expected(false);
+            locked_cwd.set_expected(initial_cwd.clone());
+            drop(locked_cwd);
+
+            let restore_hook = Cwd::install_panic_restore_hook().unwrap();
+            assert!(Cwd::install_panic_restore_hook().is_none());
+            let hook_test_dir = test_dir.to_path_buf();
+            thread!(move || {
+                Cwd::lock().set(hook_test_dir).unwrap();
+                panic!("leaving the cwd changed");
+            })
+            .expect_err("panicked");
+            assert_eq!(Cwd::lock().get().unwrap(), initial_cwd);
+
+            // the expectation at the time of the panic is restored
+            Cwd::lock().set_expected(test_dir.to_path_buf());
+            thread!(|| panic!("leaving the cwd unexpected")).expect_err("panicked");
+            assert_eq!(Cwd::lock().get().unwrap(), *test_dir);
+            Cwd::lock().set_expected(initial_cwd.clone());
+
+            // a hook set since is kept, chaining to one that no longer resets
+            let chained = Arc::new(AtomicBool::new(false));
+            let hook_chained = Arc::clone(&chained);
+            let restoring_hook = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                hook_chained.store(true, Ordering::SeqCst);
+                restoring_hook(info);
+            }));
+            drop(restore_hook);
+            thread!(|| panic!("leaving the cwd changed")).expect_err("panicked");
+            assert!(chained.load(Ordering::SeqCst));
+            assert_eq!(Cwd::lock().get().unwrap(), *test_dir);
+            drop(panic::take_hook());
+
+            drop(Cwd::install_panic_restore_hook().unwrap());
+            let mut restored_cwd = Cwd::lock();
+            restored_cwd.set(&initial_cwd).unwrap();
+            restored_cwd.clear_expected();
+            restored_cwd.track_expected(cfg!(feature = "full_expected_cwd"));
+            drop(restored_cwd);
+            Cwd::mutex().clear_poison();
+        });
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn verify_lock_held() {
+        mutex_test!(Cwd::mutex(), |locked_cwd| {
+            drop(locked_cwd);
+            let payload = thread!(|| {
+                let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+                cwd.verify_lock_held(true);
+                cwd.get()
+            })
+            .expect_err("panicked");
+            assert_eq!(
+                payload.downcast_ref::<&str>(),
+                Some(&"cwd accessed without Cwd::lock() held on this thread")
+            );
+
+            thread!(|| {
+                let mut cwd = Cwd::lock();
+                cwd.verify_lock_held(true);
+                let current_cwd = cwd.get().unwrap();
+                cwd.set(current_cwd).unwrap();
+                cwd.verify_lock_held(false);
+            })
+            .unwrap();
+
+            thread!(|| {
+                let mut tried_cwd = Cwd::try_lock().unwrap();
+                tried_cwd.verify_lock_held(true);
+                tried_cwd.get().unwrap();
+                tried_cwd.verify_lock_held(false);
+                drop(tried_cwd);
+
+                let mut backed_off_cwd = Cwd::lock_with_backoff(Duration::from_secs(10)).unwrap();
+                backed_off_cwd.verify_lock_held(true);
+                backed_off_cwd.get().unwrap();
+                backed_off_cwd.verify_lock_held(false);
+            })
+            .unwrap();
+        });
+    }
+
+    #[test]
+    #[expect(clippy::panic, reason = "poisoning the lock")]
+    fn lock_owned() {
+        mutex_test!(Cwd::mutex(), |locked_cwd| {
+            drop(locked_cwd);
+            let owned_locked_cwd = Cwd::lock_owned();
+            assert_eq!(owned_locked_cwd.get().unwrap(), env::current_dir().unwrap());
+            assert_eq!(
+                thread!(|| Cwd::try_lock().err()).unwrap(),
+                Some(TryLockKind::WouldBlock)
+            );
+            drop(owned_locked_cwd);
+
+            thread!(|| {
+                let _owned_locked_cwd = Cwd::lock_owned();
+                panic!("poisoning the Cwd lock");
+            })
+            .expect_err("panicked");
+            assert!(Cwd::mutex().is_poisoned());
+            drop(Cwd::lock_owned());
+            Cwd::mutex().clear_poison();
+        });
+    }
+
+    #[test]
+    fn lock_recursive() {
+        mutex_test!(Cwd::mutex(), |locked_cwd| {
+            drop(locked_cwd);
+            drop(Cwd::lock());
+            drop(Cwd::lock());
+
+            let payload = thread!(|| {
+                let _locked_cwd = Cwd::lock();
+                let _relocked_cwd = Cwd::lock();
+            })
+            .expect_err("panicked");
+            assert_eq!(
+                payload.downcast_ref::<&str>(),
+                Some(&"recursive cwd lock on the same thread")
+            );
+            Cwd::mutex().clear_poison();
+        });
+    }
+
+    #[test]
+    fn set_if_exists() {
+        let test_dir = test_dir!("dir1");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            assert!(cwd.set_if_exists(test_dir.join("dir1")).unwrap());
+            assert_eq!(cwd.get().unwrap(), test_dir.join("dir1"));
+
+            assert!(!cwd.set_if_exists(test_dir.join("missing")).unwrap());
+            assert_eq!(cwd.get().unwrap(), test_dir.join("dir1"));
+
+            fs::write(test_dir.join("file"), []).unwrap();
+            cwd.set_if_exists(test_dir.join("file")).unwrap_err();
+            assert_eq!(cwd.get().unwrap(), test_dir.join("dir1"));
+        });
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn set_if_exists_permission_denied() {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let test_dir = test_dir!("locked/dir1");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+            let initial_cwd = cwd.get().unwrap();
+
+            fs::set_permissions(test_dir.join("locked"), fs::Permissions::from_mode(0o000))
+                .unwrap();
+            // privileged users, e.g. root, are not denied
+            if fs::read_dir(test_dir.join("locked")).is_err() {
+                assert_eq!(
+                    cwd.set_if_exists(test_dir.join("locked/dir1"))
+                        .err()
+                        .map(|err| err.kind()),
+                    Some(ErrorKind::PermissionDenied)
+                );
+                assert_eq!(cwd.get().unwrap(), initial_cwd);
+            }
+            fs::set_permissions(test_dir.join("locked"), fs::Permissions::from_mode(0o755))
+                .unwrap();
+        });
+    }
+
+    #[test]
+    fn current_dir_arc() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        let snapshot = cwd.current_dir_arc().unwrap();
+        cwd.set("/dir1").unwrap();
+        thread::scope(|scope| {
+            for _ in 0..4_u8 {
+                let shared = Arc::clone(&snapshot);
+                scope.spawn(move || assert_eq!(&*shared, Path::new("/mock")));
+            }
+        });
+        assert_eq!(Arc::strong_count(&snapshot), 1);
+        assert_eq!(&*cwd.current_dir_arc().unwrap(), Path::new("/dir1"));
+    }
+
+    #[test]
+    fn set_checked() {
+        let test_dir = test_dir!("dir1");
+        fs::create_dir_all(test_dir.join("locked")).unwrap();
+        let mut cwd = Cwd::with_provider(
+            MockProvider::new("/mock")
+                .fail_set(test_dir.join("locked"), ErrorKind::PermissionDenied)
+                .fail_set(test_dir.join("missing"), ErrorKind::NotFound),
+        );
+
+        let denied = cwd.set_checked(test_dir.join("locked")).unwrap_err();
+        assert_eq!(denied.kind(), ErrorKind::PermissionDenied);
+        assert!(denied
+            .to_string()
+            .ends_with("exists but is not traversable"));
+        assert_eq!(
+            cwd.set_checked(test_dir.join("missing"))
+                .err()
+                .map(|err| err.kind()),
+            Some(ErrorKind::NotFound)
+        );
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+
+        {
+            let mut frozen_cwd = cwd.freeze();
+            assert_eq!(
+                frozen_cwd
+                    .set_checked(test_dir.join("dir1"))
+                    .unwrap_err()
+                    .to_string(),
+                "cwd is frozen"
+            );
+        }
+        cwd.set_checked(test_dir.join("dir1")).unwrap();
+        assert_eq!(cwd.get().unwrap(), test_dir.join("dir1"));
+    }
+
+    #[test]
+    fn set_with_fallback() {
+        let test_dir = test_dir!("primary");
+        fs::create_dir_all(test_dir.join("fallback")).unwrap();
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            let primary = test_dir.join("primary");
+            let fallback = test_dir.join("fallback");
+            assert_eq!(cwd.set_with_fallback(&primary, &fallback).unwrap(), primary);
+            assert_eq!(cwd.get().unwrap(), primary);
+
+            let missing = test_dir.join("missing");
+            assert_eq!(
+                cwd.set_with_fallback(&missing, &fallback).unwrap(),
+                fallback
+            );
+            assert_eq!(cwd.get().unwrap(), fallback);
+        });
+
+        let mut cwd = Cwd::with_provider(
+            MockProvider::new("/mock")
+                .fail_set("/locked", ErrorKind::PermissionDenied)
+                .fail_set("/missing", ErrorKind::NotFound),
+        );
+        assert_eq!(
+            cwd.set_with_fallback("/locked", "/fallback")
+                .err()
+                .map(|err| err.kind()),
+            Some(ErrorKind::PermissionDenied)
+        );
+        assert_eq!(
+            cwd.set_with_fallback("/missing", "/locked")
+                .err()
+                .map(|err| err.kind()),
+            Some(ErrorKind::PermissionDenied)
+        );
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+    }
+
+    #[test]
+    fn cd_home_root() {
+        let test_dir = test_dir!("dir1");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+            let home_var = test_utilities::restore_env_var("HOME");
+
+            env::set_var(*home_var, &*test_dir);
+            assert_eq!(cwd.cd_home().unwrap(), *test_dir);
+            assert_eq!(cwd.get().unwrap(), *test_dir);
+
+            cwd.set(test_dir.join("dir1")).unwrap();
+            let root = cwd.cd_root().unwrap();
+            assert_eq!(cwd.get().unwrap(), root);
+            assert_eq!(root.parent(), None);
+            assert!(test_dir.starts_with(&root));
+
+            #[cfg(not(windows))]
+            {
+                env::remove_var(*home_var);
+                assert_eq!(
+                    cwd.cd_home().err().map(|err| err.kind()),
+                    Some(ErrorKind::NotFound)
+                );
+                assert_eq!(cwd.get().unwrap(), root);
+            }
+        });
+    }
+
+    #[test]
+    fn cd_git_root() {
+        let test_dir = test_dir!("repo/dir1/dir2");
+        fs::create_dir_all(test_dir.join("repo/.git")).unwrap();
+        fs::create_dir_all(test_dir.join("worktree/dir1")).unwrap();
+        fs::write(test_dir.join("worktree/.git"), "gitdir: ../repo/.git").unwrap();
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            cwd.set(test_dir.join("repo/dir1/dir2")).unwrap();
+            let repo_root = cwd.cd_git_root().unwrap();
+            assert_eq!(repo_root, Some(test_dir.join("repo")));
+            assert_eq!(cwd.get().unwrap(), test_dir.join("repo"));
+
+            cwd.set(test_dir.join("worktree/dir1")).unwrap();
+            let worktree_root = cwd.cd_git_root().unwrap();
+            assert_eq!(worktree_root, Some(test_dir.join("worktree")));
+            assert_eq!(cwd.get().unwrap(), test_dir.join("worktree"));
+        });
+
+        let mut mock_cwd = Cwd::with_provider(MockProvider::new("/current_dir/no/repo"));
+        assert_eq!(mock_cwd.cd_git_root().unwrap(), None);
+        assert_eq!(mock_cwd.get().unwrap(), Path::new("/current_dir/no/repo"));
+    }
+
+    #[test]
+    fn set_from_env() {
+        mutex_test!(Cwd::mutex(), |_locked_cwd| {
+            let var = test_utilities::restore_env_var("CURRENT_DIR_SET_FROM_ENV");
+            let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+
+            env::remove_var(*var);
+            assert_eq!(
+                cwd.set_from_env(*var).err().map(|err| err.kind()),
+                Some(ErrorKind::NotFound)
+            );
+            env::set_var(*var, "");
+            assert_eq!(
+                cwd.set_from_env(*var).err().map(|err| err.kind()),
+                Some(ErrorKind::InvalidInput)
+            );
+            assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+
+            env::set_var(*var, "/dir1");
+            assert_eq!(cwd.set_from_env(*var).unwrap(), Path::new("/dir1"));
+            assert_eq!(cwd.get().unwrap(), Path::new("/dir1"));
+        });
+    }
+
+    #[test]
+    fn set_normalized() {
+        let test_dir = test_dir!("dir1");
+        fs::create_dir_all(test_dir.join("dir2")).unwrap();
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            cwd.set(test_dir.join("dir1")).unwrap();
+            cwd.set_normalized("./../dir2/.").unwrap();
+            assert_eq!(cwd.get_expected(), Some(test_dir.join("dir2")));
+            assert_eq!(cwd.get_expected().unwrap(), cwd.canonical_get().unwrap());
+
+            cwd.set_normalized("missing").unwrap_err();
+            assert_eq!(cwd.get_expected(), Some(test_dir.join("dir2")));
+        });
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn spawn_in() {
+        use std::process::Stdio;
+
+        let test_dir = test_dir!("dir1");
+        mutex_test!(
+            Cwd::mutex(),
+            |mut locked_cwd| {
+                let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+                let cwd = &mut **reset_cwd;
+                cwd.set(&*test_dir).unwrap();
+
+                let child = cwd
+                    .spawn_in("dir1", Command::new("pwd").arg("-P").stdout(Stdio::piped()))
+                    .unwrap();
+                let output = child.wait_with_output().unwrap();
+                assert!(output.status.success());
+                assert_eq!(
+                    PathBuf::from(String::from_utf8(output.stdout).unwrap().trim_end()),
+                    fs::canonicalize(test_dir.join("dir1")).unwrap()
+                );
+                assert_eq!(cwd.get().unwrap(), *test_dir);
+            },
+            Duration::from_secs(10)
+        );
+    }
+
+    #[test]
+    fn normalize_get() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        cwd.set("dir1/../dir2/./dir3/..").unwrap();
+        assert_eq!(
+            cwd.get().unwrap(),
+            Path::new("/mock/dir1/../dir2/./dir3/..")
+        );
+        assert_eq!(cwd.normalize_get().unwrap(), Path::new("/mock/dir2"));
+    }
+
+    #[test]
+    fn set_canonical_expected() {
+        let test_dir = test_dir!("dir1");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+            let initial_track_expected = cwd.track_expected;
+            cwd.track_expected(true);
+
+            cwd.set(test_dir.join("dir1/./")).unwrap();
+            assert_eq!(cwd.get_expected().unwrap(), cwd.get().unwrap());
+            cwd.set("..").unwrap();
+            assert_eq!(cwd.get_expected().unwrap(), cwd.get().unwrap());
+
+            cwd.clear_expected();
+            cwd.track_expected(initial_track_expected);
+        });
+    }
+
+    #[test]
+    fn previous() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        assert_eq!(cwd.previous().unwrap(), None);
+
+        cwd.set("/dir1").unwrap();
+        cwd.set("/dir2").unwrap();
+        for _ in 0..2_u8 {
+            assert_eq!(cwd.previous().unwrap(), Some("/dir1".into()));
+            assert_eq!(cwd.get().unwrap(), Path::new("/dir1"));
+            assert_eq!(cwd.previous().unwrap(), Some("/dir2".into()));
+            assert_eq!(cwd.get().unwrap(), Path::new("/dir2"));
+        }
+    }
+
+    #[test]
+    fn assert_unchanged() {
+        let test_dir = test_dir!();
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+            let initial_cwd = cwd.get().unwrap();
+
+            let scoped_cwd = cwd.assert_unchanged(|unchanged_cwd| {
+                unchanged_cwd
+                    .with_scope(|cwd_guard| {
+                        cwd_guard.set(&*test_dir).unwrap();
+                        cwd_guard.get()
+                    })
+                    .unwrap()
+            });
+            assert_eq!(scoped_cwd, *test_dir);
+
+            thread!(|| {
+                cwd.assert_unchanged(|changed_cwd| changed_cwd.set(&*test_dir).unwrap());
+            })
+            .expect_err("panicked");
+            assert_eq!(cwd.get().unwrap(), initial_cwd);
+        });
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn set_get_os() {
+        use std::os::unix::ffi::OsStrExt as _;
+
+        let test_dir = test_dir!();
+        let non_utf8_dir = test_dir.join(OsStr::from_bytes(b"non-utf8-\xff"));
+        fs::create_dir_all(&non_utf8_dir).unwrap();
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            cwd.set_os(non_utf8_dir.as_os_str()).unwrap();
+            let non_utf8_cwd = cwd.get_os().unwrap();
+            assert_eq!(non_utf8_cwd, non_utf8_dir.as_os_str());
+            assert_eq!(non_utf8_cwd.to_str(), None);
+        });
+    }
+
+    #[test]
+    fn set_empty() {
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+            let initial_cwd = cwd.get().unwrap();
+
+            assert_eq!(
+                cwd.set("").err().map(|err| err.kind()),
+                Some(ErrorKind::NotFound)
+            );
+            assert_eq!(cwd.get().unwrap(), initial_cwd);
+        });
+    }
+
+    #[test]
+    fn set_joined() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        cwd.set_joined("/base", ["dir1", "dir2"]).unwrap();
+        assert_eq!(cwd.get().unwrap(), Path::new("/base/dir1/dir2"));
+
+        cwd.set_joined("/base", ["dir1", "/dir2", "dir3"]).unwrap();
+        assert_eq!(cwd.get().unwrap(), Path::new("/dir2/dir3"));
+
+        cwd.set_joined("/base", [""; 0]).unwrap();
+        assert_eq!(cwd.get().unwrap(), Path::new("/base"));
+    }
+
+    #[test]
+    fn set_jail() {
+        let test_dir = test_dir!("jail/dir1");
+        fs::create_dir_all(test_dir.join("outside")).unwrap();
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+
+        cwd.set_jail(Some(test_dir.join("jail"))).unwrap();
+        cwd.set(test_dir.join("jail/dir1")).unwrap();
+        assert_eq!(
+            cwd.set(test_dir.join("jail/.."))
+                .err()
+                .map(|err| err.kind()),
+            Some(ErrorKind::PermissionDenied)
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::symlink;
+
+            symlink(test_dir.join("outside"), test_dir.join("jail/link")).unwrap();
+            assert_eq!(
+                cwd.set(test_dir.join("jail/link"))
+                    .err()
+                    .map(|err| err.kind()),
+                Some(ErrorKind::PermissionDenied)
+            );
+        }
+        assert_eq!(cwd.get().unwrap(), test_dir.join("jail/dir1"));
+
+        cwd.set_jail(None).unwrap();
+        cwd.set(test_dir.join("outside")).unwrap();
+        assert_eq!(cwd.get().unwrap(), test_dir.join("outside"));
+    }
+
+    #[test]
+    fn set_dry_run() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        cwd.set_dry_run(true);
+        cwd.set("/dir1").unwrap();
+        {
+            let mut cwd_guard = cwd.guard().unwrap();
+            cwd_guard.set("/dir2").unwrap();
+        }
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+        assert_eq!(
+            cwd.dry_run_log(),
+            [
+                PathBuf::from("/dir1"),
+                PathBuf::from("/dir2"),
+                PathBuf::from("/mock")
+            ]
+        );
+
+        cwd.set_dry_run(false);
+        cwd.set("/dir1").unwrap();
+        assert_eq!(cwd.get().unwrap(), Path::new("/dir1"));
+        assert_eq!(cwd.dry_run_log().len(), 3);
+    }
+
+    #[test]
+    fn as_ref_readonly() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        cwd.set_expected("/expected");
+        let cwd_ref = cwd.as_ref_readonly();
+        assert_eq!(cwd_ref.get().unwrap(), Path::new("/mock"));
+        assert_eq!(cwd_ref.get_expected(), Some("/expected".into()));
+    }
+
+    #[test]
+    fn history() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        cwd.set("/disabled").unwrap();
+        assert!(cwd.history().is_empty());
+
+        cwd.enable_history(2);
+        let before = Instant::now();
+        cwd.set("/dir1").unwrap();
+        cwd.set("dir2").unwrap();
+        let recorded = cwd.history();
+        assert_eq!(
+            recorded
+                .iter()
+                .map(|entry| entry.1.clone())
+                .collect::<Vec<_>>(),
+            [PathBuf::from("/dir1"), PathBuf::from("/dir1/dir2")]
+        );
+        assert!(recorded
+            .iter()
+            .zip(recorded.iter().skip(1))
+            .all(|(&(earlier, _), &(later, _))| before <= earlier && earlier <= later));
+
+        cwd.set("/dir3").unwrap();
+        assert_eq!(
+            cwd.history()
+                .into_iter()
+                .map(|(_, path)| path)
+                .collect::<Vec<_>>(),
+            [PathBuf::from("/dir1/dir2"), PathBuf::from("/dir3")]
+        );
+
+        cwd.enable_history(1);
+        assert_eq!(cwd.history().len(), 1);
+        cwd.enable_history(0);
+        cwd.set("/dir4").unwrap();
+        assert!(cwd.history().is_empty());
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        cwd.enable_history(8);
+        assert_eq!(cwd.undo().unwrap(), None);
+        for dir in ["/dir1", "/dir2", "/dir3"] {
+            cwd.set(dir).unwrap();
+        }
+
+        assert_eq!(cwd.undo().unwrap(), Some(PathBuf::from("/dir2")));
+        assert_eq!(cwd.undo().unwrap(), Some(PathBuf::from("/dir1")));
+        assert_eq!(cwd.undo().unwrap(), None);
+        assert_eq!(cwd.get().unwrap(), Path::new("/dir1"));
+
+        assert_eq!(cwd.redo().unwrap(), Some(PathBuf::from("/dir2")));
+        assert_eq!(cwd.get().unwrap(), Path::new("/dir2"));
+        assert_eq!(cwd.history().len(), 3);
+
+        cwd.set("/dir4").unwrap();
+        assert_eq!(cwd.redo().unwrap(), None);
+        assert_eq!(
+            cwd.history()
+                .into_iter()
+                .map(|(_, path)| path)
+                .collect::<Vec<_>>(),
+            [
+                PathBuf::from("/dir1"),
+                PathBuf::from("/dir2"),
+                PathBuf::from("/dir4")
+            ]
+        );
+        assert_eq!(cwd.undo().unwrap(), Some(PathBuf::from("/dir2")));
+    }
+
+    #[test]
+    fn scoped_canonical() {
+        let test_dir = test_dir!("dir1");
+        let path = test_dir.join("dir1/../dir1/.");
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        {
+            let cwd_guard = cwd.scoped_canonical(&path).unwrap();
+            assert_eq!(cwd_guard.get().unwrap(), fs::canonicalize(&path).unwrap());
+        }
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+        assert_eq!(
+            cwd.scoped_canonical(test_dir.join("missing"))
+                .err()
+                .map(|err| err.kind()),
+            Some(ErrorKind::NotFound)
+        );
+    }
+
+    #[test]
+    fn scope_with_env() {
+        mutex_test!(Cwd::mutex(), |_locked_cwd| {
+            let set_var = test_utilities::restore_env_var("CURRENT_DIR_SCOPE_WITH_ENV_SET");
+            let unset_var = test_utilities::restore_env_var("CURRENT_DIR_SCOPE_WITH_ENV_UNSET");
+            env::set_var(*set_var, "initial");
+            env::remove_var(*unset_var);
+
+            let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+            {
+                let scoped_env_cwd = cwd
+                    .scope_with_env(
+                        "dir1",
+                        vec![
+                            ((*set_var).into(), "scoped".into()),
+                            ((*unset_var).into(), "scoped".into()),
+                        ],
+                    )
+                    .unwrap();
+                assert_eq!(scoped_env_cwd.get().unwrap(), Path::new("/mock/dir1"));
+                assert_eq!(env::var_os(*set_var), Some("scoped".into()));
+                assert_eq!(env::var_os(*unset_var), Some("scoped".into()));
+            }
+            assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+            assert_eq!(env::var_os(*set_var), Some("initial".into()));
+            assert_eq!(env::var_os(*unset_var), None);
+        });
+    }
+
+    #[test]
+    #[expect(clippy::panic, reason = "testing cleanup while unwinding")]
+    fn scoped_with_cleanup() {
+        use core::panic::AssertUnwindSafe;
+
+        let test_dir = test_dir!();
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+            let initial_cwd = cwd.get().unwrap();
+
+            let cleanup_cwd = Cell::new(None);
+            drop(
+                cwd.scoped_with_cleanup(&*test_dir, || {
+                    cleanup_cwd.set(env::current_dir().ok());
+                })
+                .unwrap(),
+            );
+            assert_eq!(cleanup_cwd.take(), Some((*test_dir).clone()));
+            assert_eq!(cwd.get().unwrap(), initial_cwd);
+
+            let unwound = panic::catch_unwind(AssertUnwindSafe(|| {
+                let _scoped_cwd = cwd
+                    .scoped_with_cleanup(&*test_dir, || {
+                        cleanup_cwd.set(env::current_dir().ok());
+                    })
+                    .unwrap();
+                panic!("unwinding the scope");
+            }));
+            unwound.unwrap_err();
+            assert_eq!(cleanup_cwd.take(), Some((*test_dir).clone()));
+            assert_eq!(cwd.get().unwrap(), initial_cwd);
+        });
+    }
+
+    #[test]
+    fn try_in() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        assert_eq!(
+            cwd.try_in("/dir1", |scoped_cwd| {
+                scoped_cwd.set("dir2").map_err(|err| err.kind())
+            })
+            .unwrap(),
+            Ok(())
+        );
+        assert_eq!(cwd.get().unwrap(), Path::new("/dir1/dir2"));
+        assert_eq!(cwd.depth(), 0);
+
+        assert_eq!(
+            cwd.try_in("/dir3", |_| Err::<(), _>("scope")).unwrap(),
+            Err("scope")
+        );
+        assert_eq!(cwd.get().unwrap(), Path::new("/dir1/dir2"));
+        assert_eq!(cwd.depth(), 0);
+    }
+
+    #[test]
+    fn cwd_context() {
+        let mut cwd = Cwd::with_provider(
+            MockProvider::new("/mock").fail_set("/mock/dir1", ErrorKind::NotFound),
+        );
+        let context_err = cwd
+            .set("dir1")
+            .cwd_context("while loading plugin X")
+            .unwrap_err();
+        assert_eq!(context_err.kind(), ErrorKind::NotFound);
+        assert!(context_err
+            .to_string()
+            .starts_with("while loading plugin X: "));
+        assert_eq!(
+            context_err
+                .get_ref()
+                .and_then(Error::source)
+                .and_then(|source| source.downcast_ref::<io::Error>())
+                .map(io::Error::kind),
+            Some(ErrorKind::NotFound)
+        );
+        assert_eq!(cwd.get().cwd_context("unused").unwrap(), Path::new("/mock"));
+    }
+
+    #[test]
+    fn with_dir_and_value() {
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        assert_eq!(
+            cwd.with_dir_and_value("/dir1", || Ok::<_, &str>(1_u8))
+                .unwrap(),
+            1
+        );
+        assert!(matches!(
+            cwd.with_dir_and_value("/dir1", || Err::<u8, _>("scope")),
+            Err(CwdError::Scope("scope"))
+        ));
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+
+        let mut failing_cwd = Cwd::with_provider(
+            MockProvider::new("/mock")
+                .fail_set("/missing", ErrorKind::NotFound)
+                .fail_set("/mock", ErrorKind::PermissionDenied),
+        );
+        assert!(matches!(
+            failing_cwd.with_dir_and_value("/missing", || Ok::<_, &str>(1_u8)),
+            Err(CwdError::Setup(_))
+        ));
+        assert_eq!(failing_cwd.get().unwrap(), Path::new("/mock"));
+        assert_eq!(failing_cwd.depth(), 0);
+        assert!(matches!(
+            failing_cwd.with_dir_and_value("/dir1", || Ok::<_, &str>(1_u8)),
+            Err(CwdError::Reset(_, None))
+        ));
+        assert_eq!(failing_cwd.depth(), 0);
+
+        let mut failing_scope_cwd = Cwd::with_provider(
+            MockProvider::new("/mock").fail_set("/mock", ErrorKind::PermissionDenied),
+        );
+        let reset_err = failing_scope_cwd
+            .with_dir_and_value("/dir1", || Err::<u8, _>("scope"))
+            .unwrap_err();
+        assert!(matches!(reset_err, CwdError::Reset(_, Some("scope"))));
+        assert!(reset_err
+            .to_string()
+            .starts_with("cannot reset the current working directory"));
+    }
+
+    #[test]
+    #[expect(clippy::panic, reason = "testing the reset while unwinding")]
+    fn with_dir_and_value_panic() {
+        use core::panic::AssertUnwindSafe;
+
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+        let panicked =
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                drop(cwd.with_dir_and_value("/dir1", || -> Result<u8, &str> {
+                    panic!("scope panicked")
+                }));
+            }));
+        assert_eq!(
+            panicked.unwrap_err().downcast_ref(),
+            Some(&"scope panicked")
+        );
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+        assert_eq!(cwd.depth(), 0);
+    }
+
+    #[test]
+    fn set_when_ready() {
+        let test_dir = test_dir!("dir1");
+        let mut cwd = Cwd::with_provider(MockProvider::new("/mock"));
+
+        cwd.set_when_ready(test_dir.join("dir1"), Duration::ZERO, Duration::ZERO)
+            .unwrap();
+        assert_eq!(cwd.get().unwrap(), test_dir.join("dir1"));
+
+        let later_dir = test_dir.join("later");
+        let creator = {
+            let created_dir = later_dir.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                fs::create_dir_all(created_dir).unwrap();
+            })
+        };
+        cwd.set_when_ready(
+            &later_dir,
+            Duration::from_secs(10),
+            Duration::from_millis(1),
+        )
+        .unwrap();
+        assert_eq!(cwd.get().unwrap(), later_dir);
+        creator.join().unwrap();
+
+        assert_eq!(
+            cwd.set_when_ready(
+                test_dir.join("never"),
+                Duration::from_millis(10),
+                Duration::from_millis(1)
+            )
+            .err()
+            .map(|err| err.kind()),
+            Some(ErrorKind::TimedOut)
+        );
+        assert_eq!(cwd.get().unwrap(), later_dir);
+    }
+
+    /// A [`CwdProvider`] failing the first `failures` [`set()`](CwdProvider::set())s with `error`, that cannot
+    /// [`canonicalize()`](CwdProvider::canonicalize()).
+    struct FlakyProvider {
+        failures: usize,
+        attempts: usize,
+        error: ErrorKind,
+    }
+    impl CwdProvider for FlakyProvider {
+        fn current(&self) -> io::Result<PathBuf> {
+            Ok("/flaky".into())
+        }
+
+        fn set(&mut self, _path: &Path) -> io::Result<()> {
+            self.attempts = self.attempts.saturating_add(1);
+            if self.attempts > self.failures {
+                Ok(())
+            } else {
+                Err(self.error.into())
+            }
+        }
+
+        fn canonicalize(&self, _path: &Path) -> io::Result<PathBuf> {
+            Err(ErrorKind::NotFound.into())
+        }
+    }
+
+    #[test]
+    fn set_uncanonical_expected() {
+        let mut cwd = Cwd::with_provider(FlakyProvider {
+            failures: 0,
+            attempts: 0,
+            error: ErrorKind::NotFound,
+        });
+        cwd.track_expected(true);
+        cwd.set("/flaky/missing").unwrap();
+        assert_eq!(
+            cwd.try_clone_expected().unwrap(),
+            Path::new("/flaky/missing")
+        );
+        cwd.set("./missing/.").unwrap();
+        assert_eq!(
+            cwd.try_clone_expected().unwrap().as_os_str(),
+            Path::new("missing").as_os_str()
+        );
+    }
+
+    #[test]
+    fn set_retry() {
+        let mut cwd = Cwd::with_provider(FlakyProvider {
+            failures: 2,
+            attempts: 0,
+            error: ErrorKind::Interrupted,
+        });
+        cwd.set_retry("/dir1", 2, Duration::ZERO).unwrap();
+        assert_eq!(cwd.provider.attempts, 3);
+
+        cwd.provider.attempts = 0;
+        assert_eq!(
+            cwd.set_retry("/dir1", 1, Duration::ZERO)
+                .err()
+                .map(|err| err.kind()),
+            Some(ErrorKind::Interrupted)
+        );
+        assert_eq!(cwd.provider.attempts, 2);
+
+        cwd.provider.attempts = 0;
+        cwd.provider.error = ErrorKind::NotFound;
+        assert_eq!(
+            cwd.set_retry("/dir1", 2, Duration::ZERO)
+                .err()
+                .map(|err| err.kind()),
+            Some(ErrorKind::NotFound)
+        );
+        assert_eq!(cwd.provider.attempts, 1);
+    }
+
+    #[test]
+    fn ensure_and_set() {
+        let test_dir = test_dir!();
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            cwd.ensure_and_set(test_dir.join("dir1/dir2")).unwrap();
+            assert_eq!(cwd.get().unwrap(), test_dir.join("dir1/dir2"));
+
+            cwd.ensure_and_set(&*test_dir).unwrap();
+            fs::write(test_dir.join("file"), []).unwrap();
+            let create_err = cwd.ensure_and_set(test_dir.join("file/dir")).unwrap_err();
+            assert!(create_err
+                .to_string()
+                .starts_with("cannot create the directory: "));
+            assert!(!test_dir.join("file/dir").exists());
+            assert_eq!(cwd.get().unwrap(), *test_dir);
+
+            fs::create_dir_all(test_dir.join("jail")).unwrap();
+            cwd.set_jail(Some(test_dir.join("jail"))).unwrap();
+            let set_err = cwd.ensure_and_set(test_dir.join("outside")).unwrap_err();
+            cwd.set_jail(None).unwrap();
+            assert_eq!(set_err.kind(), ErrorKind::PermissionDenied);
+            assert!(set_err
+                .to_string()
+                .starts_with("cannot set the current working directory: "));
+            assert!(test_dir.join("outside").is_dir());
+            assert_eq!(cwd.get().unwrap(), *test_dir);
+        });
+    }
+
+    #[test]
+    fn recreate_if_missing() {
+        let test_dir = test_dir!("dir1");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            cwd.set(test_dir.join("dir1")).unwrap();
+            cwd.set_expected(test_dir.join("dir1"));
+            assert!(!cwd.recreate_if_missing().unwrap());
+
+            fs::remove_dir(test_dir.join("dir1")).unwrap();
+            assert_eq!(
+                cwd.get().err().map(|err| err.kind()),
+                Some(ErrorKind::NotFound)
+            );
+            assert!(cwd.recreate_if_missing().unwrap());
+            assert_eq!(cwd.get().unwrap(), test_dir.join("dir1"));
+            assert!(!cwd.recreate_if_missing().unwrap());
+            cwd.clear_expected();
+        });
+    }
+
+    #[test]
+    fn get_or_recreate_expected() {
+        let test_dir = test_dir!("dir1");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            cwd.set(test_dir.join("dir1")).unwrap();
+            cwd.clear_expected();
+            fs::remove_dir(test_dir.join("dir1")).unwrap();
+            assert_eq!(
+                cwd.get_or_recreate_expected().err().map(|err| err.kind()),
+                Some(ErrorKind::NotFound)
+            );
+
+            cwd.set_expected(test_dir.join("dir1"));
+            assert_eq!(
+                cwd.get_or_recreate_expected().unwrap(),
+                test_dir.join("dir1")
+            );
+            assert!(test_dir.join("dir1").is_dir());
+            assert_eq!(cwd.get().unwrap(), test_dir.join("dir1"));
+            assert_eq!(
+                cwd.get_or_recreate_expected().unwrap(),
+                test_dir.join("dir1")
+            );
+            cwd.clear_expected();
+        });
+    }
+
+    #[test]
+    #[expect(clippy::panic, reason = "testing panic behaviour")]
+    fn with_scope() {
+        let test_dir = test_dir!("dir1");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+            let initial_cwd = cwd.get().unwrap();
+
+            let scoped_cwd = cwd.with_scope(|cwd_guard| {
+                cwd_guard.set(&*test_dir).unwrap();
+                cwd_guard.get()
+            });
+            assert_eq!(scoped_cwd.unwrap(), *test_dir);
+            assert_eq!(cwd.get().unwrap(), initial_cwd);
+
+            let scoped_err = cwd.with_scope(|cwd_guard| {
+                cwd_guard.set(&*test_dir).unwrap();
+                cwd_guard.set("missing")
+            });
+            assert_eq!(
+                scoped_err.err().map(|err| err.kind()),
+                Some(ErrorKind::NotFound)
+            );
+            assert_eq!(cwd.get().unwrap(), initial_cwd);
+
+            thread!(|| {
+                cwd.with_scope::<(), _>(|cwd_guard| {
+                    cwd_guard.set(test_dir.join("dir1")).unwrap();
+                    panic!("panicking in scope");
+                })
+            })
+            .expect_err("panicked");
+            assert_eq!(cwd.get().unwrap(), initial_cwd);
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "notify")]
+    fn watch_deletion() {
+        use core::time::Duration;
+        use std::sync::mpsc;
+
+        let test_dir = test_dir!("dir1");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            cwd.set(test_dir.join("dir1")).unwrap();
+            let (deleted_tx, deleted_rx) = mpsc::channel();
+            let watch_handle = cwd
+                .watch_deletion(move || deleted_tx.send(()).unwrap())
+                .unwrap();
+            fs::remove_dir(test_dir.join("dir1")).unwrap();
+            assert_eq!(deleted_rx.recv_timeout(Duration::from_secs(10)), Ok(()));
+            drop(watch_handle);
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "tempfile")]
+    fn temp_dir_scope() {
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+            let initial_cwd = cwd.get().unwrap();
+
+            let temp_path = {
+                let temp_dir_scope = cwd.temp_dir_scope().unwrap();
+                assert!(temp_dir_scope.path().is_dir());
+                assert_eq!(
+                    temp_dir_scope.get().unwrap(),
+                    fs::canonicalize(temp_dir_scope.path()).unwrap()
+                );
+                temp_dir_scope.path().to_path_buf()
+            };
+            assert!(!temp_path.exists());
+            assert_eq!(cwd.get().unwrap(), initial_cwd);
+        });
+    }
+
+    /// A [`MockProvider`] recording whether each directory it leaves still exists.
+    #[cfg(feature = "tempfile")]
+    struct LeavingProvider {
+        mock: MockProvider,
+        left_existing: Vec<bool>,
+    }
+    #[cfg(feature = "tempfile")]
+    impl CwdProvider for LeavingProvider {
+        fn current(&self) -> io::Result<PathBuf> {
+            self.mock.current()
+        }
+
+        fn set(&mut self, path: &Path) -> io::Result<()> {
+            self.left_existing.push(self.mock.cwd.exists());
+            self.mock.set(path)
+        }
+
+        fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+            self.mock.canonicalize(path)
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "tempfile")]
+    fn temp_dir_scope_drop_order() {
+        let mut cwd = Cwd::with_provider(LeavingProvider {
+            mock: MockProvider::new("/mock"),
+            left_existing: Vec::new(),
+        });
+        let temp_dir_scope = cwd.temp_dir_scope().unwrap();
+        let temp_path = temp_dir_scope.path().to_path_buf();
+        drop(temp_dir_scope);
+        assert!(!temp_path.exists());
+        assert_eq!(cwd.provider.left_existing, [false, true]);
+        assert_eq!(cwd.get().unwrap(), Path::new("/mock"));
+    }
+}
+
 #[cfg(test)]
+#[cfg(not(loom))]
 #[cfg(feature = "full_expected_cwd")]
 mod full_expected_cwd_tests {
     use super::*;
@@ -158,7 +4377,7 @@ mod full_expected_cwd_tests {
             locked_cwd.get_expected().unwrap();
             assert_eq!(
                 *locked_cwd.expected_cwd.get_mut(),
-                Some(env::current_dir().unwrap())
+                Some((env::current_dir().unwrap(), ExpectationSource::Observed))
             );
         });
     }
@@ -175,7 +4394,7 @@ mod full_expected_cwd_tests {
             locked_cwd.get().unwrap();
             assert_eq!(
                 *locked_cwd.expected_cwd.get_mut(),
-                Some(env::current_dir().unwrap())
+                Some((env::current_dir().unwrap(), ExpectationSource::Observed))
             );
         });
     }
@@ -192,8 +4411,45 @@ mod full_expected_cwd_tests {
             );
             locked_cwd.set(&*test_dir).unwrap();
             assert_eq!(
-                locked_cwd.expected_cwd.get_mut().as_deref(),
-                Some(test_dir.as_path())
+                *locked_cwd.expected_cwd.get_mut(),
+                Some(((*test_dir).clone(), ExpectationSource::Manual))
+            );
+        });
+    }
+
+    #[test]
+    fn set_expected_eq() {
+        let test_dir = test_dir!("dir1");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            cwd.set(&*test_dir).unwrap();
+            assert!(cwd.expected_eq(&*test_dir));
+            assert!(!cwd.expected_eq(test_dir.join("dir1")));
+
+            cwd.clear_expected();
+            assert!(!cwd.expected_eq(&*test_dir));
+        });
+    }
+
+    #[test]
+    fn unexpected_set_drift() {
+        let test_dir = test_dir!("dir1");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            // compared in canonical form
+            cwd.set(test_dir.join("dir1/..")).unwrap();
+            assert!(cwd.is_expected().unwrap());
+            assert_eq!(cwd.drift().unwrap(), None);
+
+            env::set_current_dir(test_dir.join("dir1")).unwrap();
+            assert!(!cwd.is_expected().unwrap());
+            assert_eq!(
+                cwd.drift().unwrap(),
+                Some((test_dir.to_path_buf(), test_dir.join("dir1")))
             );
         });
     }
@@ -235,6 +4491,7 @@ mod full_expected_cwd_tests {
 }
 
 #[cfg(test)]
+#[cfg(not(loom))]
 #[cfg(feature = "unstable")]
 mod cwd_bench {
     extern crate test;
@@ -348,37 +4605,104 @@ mod cwd_bench {
 ///
 /// [`reset()`][reset] can be called manually to handle errors or automatically on [`drop()`][drop].
 ///
+/// As such, discarding a [`CwdGuard`] immediately resets the current working directory and is warned against.
+/// ```compile_fail
+/// # #![deny(unused_must_use)]
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+///       use current_dir::*;
+///
+///       let mut locked_cwd = Cwd::mutex().lock()?;
+///       CwdGuard::try_from(&mut *locked_cwd)?;
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+///
 /// [reset]: Self::reset()
 /// [drop]: Self::drop()
-pub struct CwdGuard<'lock> {
+#[must_use = "dropping the guard immediately resets the cwd"]
+pub struct CwdGuard<'lock, C: CwdProvider = SysProvider> {
     /// A reference to the Current working directory.
-    cwd: &'lock mut Cwd,
+    cwd: &'lock mut Cwd<C>,
     /// The initial directory to reset to.
     initial_cwd: PathBuf,
+    /// The human readable name of the scope, for diagnostics only.
+    label: Option<String>,
 }
-impl CwdGuard<'_> {
+impl<C: CwdProvider> CwdGuard<'_, C> {
     /// Resets the current working directory to the initial current working directory at the time of `self`s creation.
     ///
     /// # Errors
     /// The current directory cannot be set as per [`env::set_current_dir()`]
     #[inline]
     pub fn reset(&mut self) -> io::Result<()> {
-        self.cwd.set(&self.initial_cwd)
+        self.cwd.set(&self.initial_cwd).inspect(|&()| {
+            #[cfg(feature = "log")]
+            log::trace!(
+                target: "current_dir",
+                "cwd: reset to {}",
+                self.initial_cwd.display()
+            );
+        })
+    }
+
+    /// Sets the current working directory to `path`, which then replaces the initial current working directory as
+    /// the target of [`reset()`](Self::reset()) and [`drop()`](Self::drop()).
+    ///
+    /// A relative `path` is made [absolute](Cwd::absolute()) first, so later resets target the same directory.
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved to make `path` absolute as per [`Cwd::absolute()`] or set as per
+    /// [`env::set_current_dir()`], in which case the reset target is unchanged.
+    #[inline]
+    pub fn reset_to<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        match self.cwd.absolute(path) {
+            Ok(absolute_path) => self
+                .cwd
+                .set(&absolute_path)
+                .map(|()| self.initial_cwd = absolute_path),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates a [`CwdGuard`] under `self` that will [`reset()`](Self::reset()) to the current working directory at
+    /// the time of the call, forwarding to [`CwdGuard::try_from()`], see [`Cwd::guard()`].
+    ///
+    /// # Errors
+    /// The current directory cannot be retrieved as per [`CwdGuard::try_from()`].
+    #[inline]
+    pub fn sub_guard(&mut self) -> io::Result<CwdGuard<'_, C>> {
+        CwdGuard::try_from(self)
+    }
+}
+impl<C: CwdProvider> fmt::Debug for CwdGuard<'_, C> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CwdGuard")
+            .field("label", &self.label)
+            .field("initial_cwd", &self.initial_cwd)
+            .field("current_cwd", &self.cwd.provider.current())
+            .field("depth", &self.cwd.depth)
+            .finish()
     }
 }
-impl Drop for CwdGuard<'_> {
+impl<C: CwdProvider> Drop for CwdGuard<'_, C> {
     /// # Panics
     /// If the current directory cannot be [`reset()`](Self::reset())
     #[inline]
     fn drop(&mut self) {
-        use std::panic;
+        self.cwd.depth = self.cwd.depth.saturating_sub(1);
         if let Err(err) = self.reset() {
-            self.cwd.expected_cwd.set(Some(self.initial_cwd.clone()));
+            self.cwd.expected_cwd.set(Some((
+                self.initial_cwd.clone(),
+                ExpectationSource::PanicRecovery,
+            )));
             panic::panic_any(err)
         }
     }
 }
-impl<'lock> TryFrom<&'lock mut CwdGuard<'_>> for CwdGuard<'lock> {
+impl<'lock, C: CwdProvider> TryFrom<&'lock mut CwdGuard<'_, C>> for CwdGuard<'lock, C> {
     type Error = io::Error;
 
     /// Create a new [`CwdGuard`] under `cwd_guard` that will [`reset()`][reset] to `cwd_guard` when [`drop()`][drop] is called.
@@ -389,11 +4713,11 @@ impl<'lock> TryFrom<&'lock mut CwdGuard<'_>> for CwdGuard<'lock> {
     /// [reset]: Self::reset()
     /// [drop]: Self::drop()
     #[inline]
-    fn try_from(cwd_guard: &'lock mut CwdGuard<'_>) -> Result<Self, Self::Error> {
+    fn try_from(cwd_guard: &'lock mut CwdGuard<'_, C>) -> Result<Self, Self::Error> {
         Self::try_from(&mut *cwd_guard.cwd)
     }
 }
-impl<'lock> TryFrom<&'lock mut Cwd> for CwdGuard<'lock> {
+impl<'lock, C: CwdProvider> TryFrom<&'lock mut Cwd<C>> for CwdGuard<'lock, C> {
     type Error = io::Error;
 
     /// Creates a [`CwdGuard`] mutably borrowing the locked [`Self`].
@@ -401,26 +4725,545 @@ impl<'lock> TryFrom<&'lock mut Cwd> for CwdGuard<'lock> {
     /// # Errors
     /// The current directory cannot be retrieved as per [`env::current_dir()`]
     #[inline]
-    fn try_from(cwd: &'lock mut Cwd) -> Result<Self, Self::Error> {
-        cwd.get().map(|initial_cwd| Self { cwd, initial_cwd })
+    fn try_from(cwd: &'lock mut Cwd<C>) -> Result<Self, Self::Error> {
+        cwd.get().map(|initial_cwd| {
+            cwd.depth = cwd.depth.saturating_add(1);
+            Self {
+                cwd,
+                initial_cwd,
+                label: None,
+            }
+        })
     }
 }
-impl Deref for CwdGuard<'_> {
-    type Target = Cwd;
+impl<C: CwdProvider> From<CwdGuard<'_, C>> for PathBuf {
+    /// Consumes `cwd_guard` without [`reset()`](CwdGuard::reset())ing, keeping the current working directory, and
+    /// returns the initial directory it would have reset to.
+    #[inline]
+    fn from(cwd_guard: CwdGuard<'_, C>) -> Self {
+        let mut disarmed_guard = ManuallyDrop::new(cwd_guard);
+        disarmed_guard.cwd.depth = disarmed_guard.cwd.depth.saturating_sub(1);
+        drop(disarmed_guard.label.take());
+        mem::take(&mut disarmed_guard.initial_cwd)
+    }
+}
+impl<C: CwdProvider> Deref for CwdGuard<'_, C> {
+    type Target = Cwd<C>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.cwd
+    }
+}
+impl<C: CwdProvider> DerefMut for CwdGuard<'_, C> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.cwd
+    }
+}
+
+/// Sets the current working directory to the target of [`Cwd::set_deferred()`] when dropped.
+#[must_use = "dropping the guard immediately sets the cwd"]
+pub struct DeferredGuard<'cwd, C: CwdProvider = SysProvider> {
+    /// The current working directory to set.
+    cwd: &'cwd mut Cwd<C>,
+    /// The directory to set the current working directory to.
+    target: PathBuf,
+}
+impl<C: CwdProvider> Drop for DeferredGuard<'_, C> {
+    /// The target is left as the [expectation](Cwd::expected_with_source()) with
+    /// [`ExpectationSource::PanicRecovery`] if it cannot be [`set()`](Cwd::set()).
+    ///
+    /// # Panics
+    /// If the current directory cannot be [`set()`](Cwd::set()) to the target, unless the current thread is already
+    /// panicking, where the error is logged with the `log` feature instead.
+    #[inline]
+    #[expect(clippy::panic, reason = "drop cannot return the error")]
+    fn drop(&mut self) {
+        if let Err(err) = self.cwd.set(&self.target) {
+            self.cwd.expected_cwd.set(Some((
+                self.target.clone(),
+                ExpectationSource::PanicRecovery,
+            )));
+            if thread::panicking() {
+                #[cfg(feature = "log")]
+                log::error!(
+                    target: "current_dir",
+                    "cwd: deferred set to {} failed: {err}",
+                    self.target.display()
+                );
+            } else {
+                panic::panic_any(err)
+            }
+        }
+    }
+}
+impl<C: CwdProvider> Deref for DeferredGuard<'_, C> {
+    type Target = Cwd<C>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.cwd
+    }
+}
+impl<C: CwdProvider> DerefMut for DeferredGuard<'_, C> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.cwd
+    }
+}
+
+/// Keeps the current working directory frozen, as per [`Cwd::freeze()`], until dropped.
+#[must_use = "dropping the guard immediately unfreezes the cwd"]
+pub struct FreezeGuard<'cwd, C: CwdProvider = SysProvider> {
+    /// The frozen current working directory.
+    cwd: &'cwd mut Cwd<C>,
+}
+impl<C: CwdProvider> Drop for FreezeGuard<'_, C> {
+    #[inline]
+    fn drop(&mut self) {
+        self.cwd.frozen = self.cwd.frozen.saturating_sub(1);
+    }
+}
+impl<C: CwdProvider> Deref for FreezeGuard<'_, C> {
+    type Target = Cwd<C>;
 
     #[inline]
     fn deref(&self) -> &Self::Target {
         self.cwd
     }
 }
-impl DerefMut for CwdGuard<'_> {
+impl<C: CwdProvider> DerefMut for FreezeGuard<'_, C> {
     #[inline]
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.cwd
     }
 }
 
+/// A [`CwdGuard`] that also restores the environment variables set by [`Cwd::scope_with_env()`] on
+/// [`drop()`](Self::drop()), before the current working directory.
+#[must_use = "dropping the guard immediately resets the cwd and environment variables"]
+pub struct ScopedEnvCwd<'lock, C: CwdProvider = SysProvider> {
+    /// The environment variables set and their previous values, if any.
+    previous_vars: Vec<(OsString, Option<OsString>)>,
+    /// The guard resetting the current working directory, dropped after the environment variables are restored.
+    cwd_guard: CwdGuard<'lock, C>,
+}
+impl<C: CwdProvider> Drop for ScopedEnvCwd<'_, C> {
+    /// # Panics
+    /// If the current directory cannot be [`reset()`](CwdGuard::reset()) as per [`CwdGuard::drop()`].
+    #[inline]
+    fn drop(&mut self) {
+        for (key, previous_value) in self.previous_vars.drain(..).rev() {
+            match previous_value {
+                Some(value) => env::set_var(key, value),
+                None => env::remove_var(key),
+            }
+        }
+    }
+}
+impl<'lock, C: CwdProvider> Deref for ScopedEnvCwd<'lock, C> {
+    type Target = CwdGuard<'lock, C>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.cwd_guard
+    }
+}
+impl<C: CwdProvider> DerefMut for ScopedEnvCwd<'_, C> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cwd_guard
+    }
+}
+
+/// A [`CwdGuard`] that also calls the cleanup of [`Cwd::scoped_with_cleanup()`] on [`drop()`](Self::drop()), before
+/// the current working directory is reset.
+#[must_use = "dropping the guard immediately cleans up and resets the cwd"]
+pub struct ScopedCleanupCwd<'lock, F: FnOnce(), C: CwdProvider = SysProvider> {
+    /// The cleanup to call, taken when dropped.
+    cleanup: Option<F>,
+    /// The guard resetting the current working directory, dropped after the cleanup, even if it panics.
+    cwd_guard: CwdGuard<'lock, C>,
+}
+impl<F: FnOnce(), C: CwdProvider> Drop for ScopedCleanupCwd<'_, F, C> {
+    /// # Panics
+    /// If the cleanup panics or the current directory cannot be [`reset()`](CwdGuard::reset()) as per
+    /// [`CwdGuard::drop()`].
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(cleanup) = self.cleanup.take() {
+            cleanup();
+        }
+    }
+}
+impl<'lock, F: FnOnce(), C: CwdProvider> Deref for ScopedCleanupCwd<'lock, F, C> {
+    type Target = CwdGuard<'lock, C>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.cwd_guard
+    }
+}
+impl<F: FnOnce(), C: CwdProvider> DerefMut for ScopedCleanupCwd<'_, F, C> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cwd_guard
+    }
+}
+
+/// A [`CwdGuard`] that has set the current working directory to the [`TempDir`](tempfile::TempDir) of
+/// [`Cwd::temp_dir_scope()`], resetting it before the directory is removed on drop.
+#[cfg(feature = "tempfile")]
+#[must_use = "dropping the guard immediately resets the cwd and removes the directory"]
+pub struct TempDirScope<'lock, C: CwdProvider = SysProvider> {
+    /// The guard resetting the current working directory, dropped before the `temp_dir` is removed.
+    cwd_guard: CwdGuard<'lock, C>,
+    /// The temporary directory, removed when dropped.
+    temp_dir: tempfile::TempDir,
+}
+#[cfg(feature = "tempfile")]
+impl<C: CwdProvider> TempDirScope<'_, C> {
+    /// The path of the temporary directory.
+    #[inline]
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        self.temp_dir.path()
+    }
+}
+#[cfg(feature = "tempfile")]
+impl<'lock, C: CwdProvider> Deref for TempDirScope<'lock, C> {
+    type Target = CwdGuard<'lock, C>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.cwd_guard
+    }
+}
+#[cfg(feature = "tempfile")]
+impl<C: CwdProvider> DerefMut for TempDirScope<'_, C> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cwd_guard
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(loom))]
+#[cfg(feature = "log")]
+mod log_tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// A [`log::Log`] capturing the records targeting this crate.
+    struct CapturingLogger {
+        /// The level and message of each captured record.
+        records: Mutex<Vec<(log::Level, String)>>,
+    }
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+            metadata.target() == "current_dir"
+        }
+
+        fn log(&self, record: &log::Record<'_>) {
+            if self.enabled(record.metadata()) {
+                self.records
+                    .lock()
+                    .unwrap()
+                    .push((record.level(), record.args().to_string()));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// The logger installed for all tests.
+    static LOGGER: CapturingLogger = CapturingLogger {
+        records: Mutex::new(Vec::new()),
+    };
+
+    #[test]
+    fn set_reset_records() {
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let mut cwd = Cwd::with_provider(
+            MockProvider::new("/log_mock").fail_set("/log_mock/dir2", io::ErrorKind::NotFound),
+        );
+        {
+            let mut cwd_guard = cwd.guard().unwrap();
+            cwd_guard.set("/log_mock/dir1").unwrap();
+            cwd_guard.set("/log_mock/dir2").unwrap_err();
+        }
+        let records: Vec<_> = LOGGER
+            .records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|record| record.1.contains("/log_mock"))
+            .cloned()
+            .collect();
+        assert_eq!(
+            records,
+            [
+                (
+                    log::Level::Debug,
+                    "cwd: /log_mock -> /log_mock/dir1".to_owned()
+                ),
+                (
+                    log::Level::Debug,
+                    "cwd: /log_mock/dir1 -> /log_mock".to_owned()
+                ),
+                (log::Level::Trace, "cwd: reset to /log_mock".to_owned()),
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(loom))]
+#[cfg(all(feature = "signal", unix))]
+mod signal_tests {
+    use super::*;
+    use signal_hook::consts::{SIGTERM, SIGWINCH};
+    use std::os::unix::process::ExitStatusExt as _;
+
+    #[test]
+    fn install_signal_restore() {
+        let test_dir = test_dir!();
+        mutex_test!(
+            Cwd::mutex(),
+            |mut locked_cwd| {
+                let initial_cwd = locked_cwd.get().unwrap();
+                locked_cwd.set_expected(initial_cwd.clone());
+                let restore_handle = locked_cwd.install_signal_restore(&[SIGWINCH]).unwrap();
+
+                locked_cwd.set(&*test_dir).unwrap();
+                low_level::raise(SIGWINCH).unwrap();
+                thread::sleep(Duration::from_millis(50));
+                assert_eq!(locked_cwd.get().unwrap(), *test_dir);
+                drop(locked_cwd);
+
+                let start = Instant::now();
+                while Cwd::lock().get().unwrap() != initial_cwd
+                    && start.elapsed() < Duration::from_secs(5)
+                {
+                    low_level::raise(SIGWINCH).unwrap();
+                    thread::sleep(Duration::from_millis(1));
+                }
+                assert_eq!(Cwd::lock().get().unwrap(), initial_cwd);
+                drop(restore_handle);
+            },
+            Duration::from_secs(10)
+        );
+    }
+
+    #[test]
+    #[expect(
+        clippy::significant_drop_tightening,
+        reason = "the lock is held by the guard while the signal is handled"
+    )]
+    fn restore_on_signal_with_guard() {
+        let test_dir = test_dir!();
+        mutex_test!(
+            Cwd::mutex(),
+            |locked_cwd| {
+                let initial_cwd = locked_cwd.get().unwrap();
+                drop(locked_cwd);
+                let mut guarded_cwd = Cwd::lock();
+                let mut cwd_guard = guarded_cwd.guard().unwrap();
+                cwd_guard.set(&*test_dir).unwrap();
+
+                let winch_baseline = initial_cwd.clone();
+                thread::spawn(move || restore_on_signal(&winch_baseline, SIGWINCH))
+                    .join()
+                    .unwrap();
+                assert_eq!(env::current_dir().unwrap(), *test_dir);
+
+                let term_baseline = initial_cwd.clone();
+                thread::spawn(move || restore_on_signal(&term_baseline, SIGTERM))
+                    .join()
+                    .unwrap();
+                assert_eq!(env::current_dir().unwrap(), initial_cwd);
+                drop(cwd_guard);
+                assert_eq!(guarded_cwd.get().unwrap(), initial_cwd);
+            },
+            Duration::from_secs(10)
+        );
+    }
+
+    #[test]
+    fn signal_default_after_drop() {
+        const CHILD_VAR: &str = "CURRENT_DIR_SIGNAL_DEFAULT_CHILD";
+        if env::var_os(CHILD_VAR).is_some() {
+            drop(Cwd::lock().install_signal_restore(&[SIGTERM]).unwrap());
+            low_level::raise(SIGTERM).unwrap();
+            thread::sleep(Duration::from_secs(10));
+        } else {
+            let status = Command::new(env::current_exe().unwrap())
+                .args(["--exact", "signal_tests::signal_default_after_drop"])
+                .env(CHILD_VAR, "1")
+                .status()
+                .unwrap();
+            assert_eq!(status.signal(), Some(SIGTERM));
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(loom))]
+#[cfg(feature = "tokio")]
+mod tokio_tests {
+    use super::*;
+
+    #[test]
+    fn run_in_tasks() {
+        use tokio::{runtime::Builder, task::yield_now};
+
+        let test_dir = test_dir!("dir1");
+        fs::create_dir_all(test_dir.join("dir2")).unwrap();
+        mutex_test!(
+            Cwd::mutex(),
+            |locked_cwd| {
+                let initial_cwd = locked_cwd.get().unwrap();
+                drop(locked_cwd);
+                let runtime = Builder::new_multi_thread()
+                    .worker_threads(2)
+                    .build()
+                    .unwrap();
+                runtime.block_on(async {
+                    let tasks = ["dir1", "dir2"].map(|sub_dir| {
+                        let dir = test_dir.join(sub_dir);
+                        tokio::spawn(async move {
+                            for _ in 0..10_u8 {
+                                AsyncCwd::run_in(&dir, async {
+                                    yield_now().await;
+                                    assert_eq!(env::current_dir().unwrap(), dir);
+                                })
+                                .await
+                                .unwrap();
+                            }
+                        })
+                    });
+                    for task in tasks {
+                        task.await.unwrap();
+                    }
+                });
+                assert_eq!(Cwd::lock().get().unwrap(), initial_cwd);
+            },
+            Duration::from_secs(10)
+        );
+    }
+
+    #[test]
+    #[expect(
+        clippy::significant_drop_tightening,
+        reason = "the lock is contended while cancelling"
+    )]
+    fn lock_cancelled() {
+        use tokio::runtime::Builder;
+
+        let test_dir = test_dir!();
+        mutex_test!(
+            Cwd::mutex(),
+            |locked_cwd| {
+                drop(locked_cwd);
+                let jail = Some(fs::canonicalize(&*test_dir).unwrap());
+                let runtime = Builder::new_multi_thread()
+                    .worker_threads(2)
+                    .build()
+                    .unwrap();
+                runtime.block_on(async {
+                    let mut async_locked_cwd = AsyncCwd::lock().await;
+                    async_locked_cwd.set_jail(jail.clone()).unwrap();
+                    async_locked_cwd.set_expected(&*test_dir);
+                    async_locked_cwd.frozen = 1;
+                    drop(async_locked_cwd);
+
+                    let mut contended_cwd = Cwd::lock();
+                    contended_cwd.set_jail(jail.clone()).unwrap();
+                    contended_cwd.set_expected(&*test_dir);
+                    contended_cwd.frozen = 1;
+                    let cancelled = tokio::spawn(AsyncCwd::lock());
+                    thread::sleep(Duration::from_millis(10));
+                    cancelled.abort();
+                    assert!(cancelled.await.err().is_some_and(|err| err.is_cancelled()));
+                    drop(contended_cwd);
+
+                    let mut relocked_async_cwd = AsyncCwd::lock().await;
+                    assert_eq!(relocked_async_cwd.jail, jail);
+                    assert_eq!(relocked_async_cwd.frozen, 1);
+                    assert_eq!(
+                        relocked_async_cwd.get_expected().as_deref(),
+                        Some(&**test_dir)
+                    );
+                    relocked_async_cwd.frozen = 0;
+                    relocked_async_cwd.clear_expected();
+                    relocked_async_cwd.set_jail(None).unwrap();
+                });
+                let mut relocked_cwd = Cwd::lock();
+                assert_eq!(relocked_cwd.jail, jail);
+                assert_eq!(relocked_cwd.frozen, 1);
+                assert_eq!(relocked_cwd.get_expected().as_deref(), Some(&**test_dir));
+                relocked_cwd.frozen = 0;
+                relocked_cwd.clear_expected();
+                relocked_cwd.set_jail(None).unwrap();
+                drop(relocked_cwd);
+            },
+            Duration::from_secs(10)
+        );
+    }
+
+    #[test]
+    fn run_in_with_lock() {
+        use tokio::{runtime::Builder, task::yield_now};
+
+        let test_dir = test_dir!("async");
+        fs::create_dir_all(test_dir.join("sync")).unwrap();
+        mutex_test!(
+            Cwd::mutex(),
+            |locked_cwd| {
+                let initial_cwd = locked_cwd.get().unwrap();
+                drop(locked_cwd);
+                let sync_dir = test_dir.join("sync");
+                let sync_thread = thread::spawn(move || {
+                    for _ in 0..10_u8 {
+                        Cwd::lock()
+                            .with_scope(|cwd_guard| {
+                                cwd_guard.set(&sync_dir).map(|()| {
+                                    thread::sleep(Duration::from_millis(1));
+                                    assert_eq!(env::current_dir().unwrap(), sync_dir);
+                                })
+                            })
+                            .unwrap();
+                    }
+                });
+                let async_dir = test_dir.join("async");
+                let runtime = Builder::new_multi_thread()
+                    .worker_threads(2)
+                    .build()
+                    .unwrap();
+                runtime.block_on(async {
+                    for _ in 0..10_u8 {
+                        AsyncCwd::run_in(&async_dir, async {
+                            yield_now().await;
+                            thread::sleep(Duration::from_millis(1));
+                            assert_eq!(env::current_dir().unwrap(), async_dir);
+                        })
+                        .await
+                        .unwrap();
+                    }
+                });
+                sync_thread.join().unwrap();
+                assert_eq!(Cwd::lock().get().unwrap(), initial_cwd);
+            },
+            Duration::from_secs(10)
+        );
+    }
+}
+
 #[cfg(test)]
+#[cfg(not(loom))]
 mod guard_tests {
     use super::*;
 
@@ -452,6 +5295,45 @@ mod guard_tests {
         });
     }
 
+    #[test]
+    #[expect(clippy::panic, reason = "testing the deferred set while unwinding")]
+    fn set_deferred() {
+        use core::panic::AssertUnwindSafe;
+
+        let mut cwd = Cwd::with_provider(
+            MockProvider::new("/mock").fail_set("/missing", io::ErrorKind::NotFound),
+        );
+        {
+            let deferred_cwd = cwd.set_deferred("/dir1".into());
+            assert_eq!(deferred_cwd.get().unwrap(), Path::new("/mock"));
+        }
+        assert_eq!(cwd.get().unwrap(), Path::new("/dir1"));
+
+        let unwound = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _deferred_cwd = cwd.set_deferred("/dir2".into());
+            panic!("unwinding the deferred set");
+        }));
+        assert_eq!(
+            unwound.unwrap_err().downcast_ref(),
+            Some(&"unwinding the deferred set")
+        );
+        assert_eq!(cwd.get().unwrap(), Path::new("/dir2"));
+
+        let failed = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _deferred_cwd = cwd.set_deferred("/missing".into());
+            panic!("unwinding the failed deferred set");
+        }));
+        assert_eq!(
+            failed.unwrap_err().downcast_ref(),
+            Some(&"unwinding the failed deferred set")
+        );
+        assert_eq!(cwd.get().unwrap(), Path::new("/dir2"));
+        assert_eq!(
+            cwd.expected_with_source(),
+            Some((PathBuf::from("/missing"), ExpectationSource::PanicRecovery))
+        );
+    }
+
     #[test]
     fn test_guard_drop() {
         let test_dir = test_dir!();
@@ -486,6 +5368,29 @@ mod guard_tests {
         });
     }
 
+    #[test]
+    fn guard_drop_panic_expectation_source() {
+        let test_dir = test_dir!("dir1");
+        mutex_test!(Cwd::mutex(), |mut locked_cwd| {
+            let mut reset_cwd = test_utilities::reset_cwd(&mut locked_cwd);
+            let cwd = &mut **reset_cwd;
+
+            cwd.set(test_dir.join("dir1")).unwrap();
+            thread!(|| {
+                let mut cwd_guard = CwdGuard::try_from(&mut *cwd).unwrap();
+                cwd_guard.set(&*test_dir).unwrap();
+
+                // cause panic on drop
+                fs::remove_dir(test_dir.join("dir1")).unwrap();
+            })
+            .expect_err("panicked");
+            assert_eq!(
+                cwd.expected_with_source(),
+                Some((test_dir.join("dir1"), ExpectationSource::PanicRecovery))
+            );
+        });
+    }
+
     #[test]
     fn test_guard_recursive() {
         let test_dir = test_dir!("dir1/dir2");
@@ -513,3 +5418,121 @@ mod guard_tests {
         });
     }
 }
+
+#[cfg(test)]
+#[cfg(loom)]
+mod loom_tests {
+    use super::*;
+    use loom::thread;
+    use std::{fs, panic};
+
+    /// Recovers from an expectation left behind by a panicking [`CwdGuard`], returning the recovered directory.
+    fn recover(cwd: &mut Cwd) -> Option<PathBuf> {
+        clone_cell_value(&cwd.expected_cwd).map(|(expected_cwd, source)| {
+            assert_eq!(source, ExpectationSource::PanicRecovery);
+            fs::create_dir_all(&expected_cwd).unwrap();
+            cwd.set(&expected_cwd).unwrap();
+            cwd.expected_cwd.set(None);
+            expected_cwd
+        })
+    }
+
+    /// Models a [`CwdGuard`] panicking mid-scope while another thread contends for the [`Cwd`] lock.
+    ///
+    /// `loom`'s [`Mutex`] does not model poisoning, so the panic is caught within the critical section, leaving the
+    /// expectation recorded by the panicking [`CwdGuard`] as the state to recover from.
+    #[test]
+    fn guard_panic_recovery() {
+        let initial_cwd = env::current_dir().unwrap();
+        let test_dir = env::temp_dir().join("current_dir loom guard_panic_recovery");
+        loom::model(move || {
+            fs::create_dir_all(&test_dir).unwrap();
+
+            let panicking_dir = test_dir.clone();
+            let panicking = thread::spawn(move || {
+                let mut locked_cwd = Cwd::mutex().lock().unwrap();
+                locked_cwd.set(&panicking_dir).unwrap();
+                panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                    // cause panic in `cwd_guard` drop
+                    let mut cwd_guard = CwdGuard::try_from(&mut *locked_cwd).unwrap();
+                    cwd_guard.set(env::temp_dir()).unwrap();
+                    fs::remove_dir(&panicking_dir).unwrap();
+                    drop(cwd_guard);
+                }))
+                .expect_err("panicked");
+                drop(locked_cwd);
+            });
+            let recovering = thread::spawn(|| {
+                let mut locked_cwd = Cwd::mutex().lock().unwrap();
+                let recovered = recover(&mut locked_cwd);
+                drop(locked_cwd);
+                recovered
+            });
+
+            panicking.join().unwrap();
+            let recovered = recovering.join().unwrap();
+
+            let mut locked_cwd = Cwd::mutex().lock().unwrap();
+            // the recovering thread may have acquired the lock first, leaving nothing to recover
+            let recovered_cwd = recovered.or_else(|| recover(&mut locked_cwd));
+            assert_eq!(recovered_cwd.as_ref(), Some(&test_dir));
+            assert_eq!(clone_cell_value(&locked_cwd.expected_cwd), None);
+            assert_eq!(locked_cwd.get().unwrap(), test_dir);
+
+            locked_cwd.set(&initial_cwd).unwrap();
+            drop(locked_cwd);
+        });
+    }
+
+    /// Models a scope panicking while holding a [`CwdGuard`] as another thread contends for the [`Cwd`] lock and
+    /// changes the current working directory itself.
+    ///
+    /// A panic unwinding through `loom`'s [`MutexGuard`] fails the model when the lock is next acquired, so the panic
+    /// is caught with the lock still held, unwinding only through the [`CwdGuard`] which resets the current working
+    /// directory for the contending thread.
+    #[test]
+    #[expect(clippy::panic, reason = "panicking while holding the guard")]
+    fn scope_panic_reset() {
+        let initial_cwd = env::current_dir().unwrap();
+        let test_dir = env::temp_dir().join("current_dir loom scope_panic_reset");
+        loom::model(move || {
+            fs::create_dir_all(test_dir.join("panicking")).unwrap();
+            fs::create_dir_all(test_dir.join("recovering")).unwrap();
+
+            let panicking_dir = test_dir.join("panicking");
+            let panicking = thread::spawn(move || {
+                let mut locked_cwd = Cwd::lock();
+                let unwound = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                    let mut cwd_guard = CwdGuard::try_from(&mut *locked_cwd).unwrap();
+                    cwd_guard.set(&panicking_dir).unwrap();
+                    panic!("panicking while holding the guard");
+                }));
+                assert_eq!(
+                    unwound.unwrap_err().downcast_ref(),
+                    Some(&"panicking while holding the guard")
+                );
+                drop(locked_cwd);
+            });
+            let recovering_dir = test_dir.join("recovering");
+            let recovering_initial_cwd = initial_cwd.clone();
+            let recovering = thread::spawn(move || {
+                let mut locked_cwd = Cwd::lock();
+                assert_eq!(locked_cwd.get().unwrap(), recovering_initial_cwd);
+                locked_cwd
+                    .with_scope(|cwd_guard| {
+                        cwd_guard.set(&recovering_dir).unwrap();
+                        cwd_guard.get()
+                    })
+                    .unwrap()
+            });
+
+            panicking.join().unwrap();
+            assert_eq!(recovering.join().unwrap(), test_dir.join("recovering"));
+
+            let locked_cwd = Cwd::lock();
+            assert_eq!(locked_cwd.get().unwrap(), initial_cwd);
+            assert_eq!(clone_cell_value(&locked_cwd.expected_cwd), None);
+            drop(locked_cwd);
+        });
+    }
+}
@@ -1,9 +1,35 @@
 //! Private module for the [`Sealed`] trait.
 
-use super::{Cwd, CwdGuard};
+use std::io;
+
+#[cfg(all(feature = "signal", unix))]
+use super::SignalRestoreHandle;
+#[cfg(feature = "tempfile")]
+use super::TempDirScope;
+#[cfg(feature = "tokio")]
+use super::{AsyncCwdGuard, AsyncLockedCwd};
+use super::{
+    Cwd, CwdGuard, CwdProvider, CwdRef, DeferredGuard, FreezeGuard, LockedCwd, PanicRestoreHook,
+    ScopedCleanupCwd, ScopedEnvCwd,
+};
 
 /// Trait to protect against downstream implementations.
-#[expect(dead_code, reason = "Designed to prevent use")]
 pub trait Sealed {}
-impl Sealed for Cwd {}
-impl Sealed for CwdGuard<'_> {}
+impl<C> Sealed for Cwd<C> {}
+impl<T> Sealed for io::Result<T> {}
+impl<C: CwdProvider> Sealed for CwdGuard<'_, C> {}
+impl<C> Sealed for CwdRef<'_, C> {}
+impl<C: CwdProvider> Sealed for DeferredGuard<'_, C> {}
+impl<C: CwdProvider> Sealed for FreezeGuard<'_, C> {}
+impl Sealed for LockedCwd {}
+impl Sealed for PanicRestoreHook {}
+impl<F: FnOnce(), C: CwdProvider> Sealed for ScopedCleanupCwd<'_, F, C> {}
+impl<C: CwdProvider> Sealed for ScopedEnvCwd<'_, C> {}
+#[cfg(all(feature = "signal", unix))]
+impl Sealed for SignalRestoreHandle {}
+#[cfg(feature = "tempfile")]
+impl<C: CwdProvider> Sealed for TempDirScope<'_, C> {}
+#[cfg(feature = "tokio")]
+impl Sealed for AsyncCwdGuard<'_> {}
+#[cfg(feature = "tokio")]
+impl Sealed for AsyncLockedCwd {}
@@ -3,7 +3,7 @@ use core::{
     time::Duration,
 };
 use std::{
-    env::temp_dir,
+    env::{self, temp_dir},
     panic,
     sync::{Mutex, MutexGuard, TryLockError},
     thread::{self, yield_now},
@@ -230,6 +230,34 @@ pub fn reset_cwd(locked_cwd: &mut Cwd) -> WithDrop<&mut Cwd, impl FnOnce(&mut Cw
     with_drop(locked_cwd, reset_cwd_fn)
 }
 
+/// Returns the environment variable `key` that will be restored to its value at the time of the call to
+/// [`restore_env_var()`], or removed if it was unset, when dropped.
+pub fn restore_env_var(key: &str) -> WithDrop<&str, impl FnOnce(&str)> {
+    let initial_value = env::var_os(key);
+    let restore_env_var_fn = move |var: &str| match initial_value {
+        Some(value) => env::set_var(var, value),
+        None => env::remove_var(var),
+    };
+    with_drop(key, restore_env_var_fn)
+}
+
+#[test]
+fn restores_env_var() {
+    const SET_VAR: &str = "CURRENT_DIR_TEST_RESTORE_ENV_VAR_SET";
+    const UNSET_VAR: &str = "CURRENT_DIR_TEST_RESTORE_ENV_VAR_UNSET";
+    env::set_var(SET_VAR, "initial");
+    env::remove_var(UNSET_VAR);
+    {
+        let set_var = restore_env_var(SET_VAR);
+        let unset_var = restore_env_var(UNSET_VAR);
+        env::remove_var(*set_var);
+        env::set_var(*unset_var, "changed");
+    }
+    assert_eq!(env::var_os(SET_VAR), Some("initial".into()));
+    assert_eq!(env::var_os(UNSET_VAR), None);
+    env::remove_var(SET_VAR);
+}
+
 #[test]
 fn test_reset_cwd() {
     mutex_block_timeout_10s!({